@@ -4,55 +4,606 @@
 //
 // Copyright (c) DUSK NETWORK. All rights reserved.
 
+use std::fmt;
 use std::path::PathBuf;
+use std::str::FromStr;
 
-use clap::Parser;
+use clap::{Parser, Subcommand, ValueEnum};
+
+use crate::output::Funding;
+use crate::polling;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 #[command(propagate_version = true)]
 pub struct Args {
+    #[command(flatten)]
+    pub global: GlobalArgs,
+
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+/// Options shared by all subcommands
+#[derive(clap::Args, Debug, Clone)]
+pub struct GlobalArgs {
     /// Blockchain access config directory
-    #[clap(long, default_value = "./config.toml")]
+    #[clap(long, default_value = "./config.toml", global = true)]
     pub config_path: PathBuf,
 
     /// Seed phrase [default: ``]
-    #[clap(short, long, default_value_t = String::from(""))]
+    #[clap(
+        short,
+        long,
+        default_value_t = String::from(""),
+        global = true,
+        env = "DUSK_SEED",
+        hide_env_values = true
+    )]
     pub seed: String,
 
-    /// Gas limit [default: `500000000`]
-    #[clap(long, default_value_t = 500000000)]
-    pub gas_limit: u64,
+    /// Moonlight secret key [default: ``]
+    #[clap(
+        short,
+        long,
+        default_value_t = String::from(""),
+        global = true,
+        env = "DUSK_MOONLIGHT_KEY",
+        hide_env_values = true
+    )]
+    pub moonlight: String,
 
-    /// Gas price [default: `1`]
-    #[clap(long, default_value_t = 2000)]
-    pub gas_price: u64,
+    /// Encrypted keystore holding the seed phrase or Moonlight secret key,
+    /// or rusk wallet `.dat` file
+    #[clap(long, global = true, env = "DUSK_KEYSTORE")]
+    pub keystore: Option<PathBuf>,
 
-    /// Path to contract code
-    #[clap(short, long, default_value = "")]
-    pub contract_path: PathBuf,
+    /// Index of the Phoenix key or Moonlight account derived from the seed
+    /// funding transactions [default: `0`]
+    #[clap(long, default_value_t = 0, global = true)]
+    pub profile_index: u8,
 
-    /// Hexadecimal string of contract's owner [default: ``]
-    #[clap(short, long, default_value_t = String::from(""))]
-    pub owner: String,
+    /// Number of profiles, from index 0, whose notes are scanned for in the
+    /// same pass as those of the funding profile [default: `1`]
+    #[clap(long, default_value_t = 1, global = true)]
+    pub scan_profiles: u8,
 
-    /// Nonce [default: `0`]
-    #[clap(short, long, default_value_t = 0)]
-    pub nonce: u64,
+    /// Source of funds, e.g. the Moonlight account derived from a seed
+    /// [default: `moonlight` given a Moonlight key, `phoenix` otherwise]
+    #[clap(long, value_enum, global = true)]
+    pub funding: Option<Funding>,
 
-    /// Hexadecimal string of contract's constructor arguments [default: ``]
-    #[clap(short, long, default_value_t = String::from(""))]
-    pub args: String,
+    /// Gas limit, or `auto` to estimate it [default: `500000000`]
+    #[clap(long, default_value_t = GasLimit::Fixed(DEFAULT_GAS_LIMIT), global = true)]
+    pub gas_limit: GasLimit,
+
+    /// Safety margin added to estimated gas, in percent [default: `20`]
+    #[clap(long, default_value_t = 20, global = true)]
+    pub gas_margin: u64,
+
+    /// Gas price [default: `1`]
+    #[clap(long, default_value_t = 2000, global = true)]
+    pub gas_price: u64,
 
     /// Starting block height for scanning notes [default: `0`]
-    #[clap(short, long, default_value_t = 0)]
+    #[clap(short, long, default_value_t = 0, global = true)]
     pub block_height: u64,
 
     /// Relative block height [default: `0`]
-    #[clap(short, long, default_value_t = 0)]
+    #[clap(short, long, default_value_t = 0, global = true)]
     pub relative_height: u64,
 
-    /// Moonlight secret key [default: ``]
+    /// Output format [default: `text`]
+    #[clap(long, value_enum, default_value_t = OutputFormat::Text, global = true)]
+    pub output: OutputFormat,
+
+    /// Stop after preverification, without propagating transactions
+    #[clap(long, global = true)]
+    pub dry_run: bool,
+
+    /// File the serialized transaction is written to in dry run mode
+    #[clap(long, requires = "dry_run", global = true)]
+    pub tx_file: Option<PathBuf>,
+
+    /// Wait for `none`, `included`, a number of confirmations or
+    /// `finalized` [default: `included`]
+    #[clap(long, default_value_t = WaitFor::Included, global = true)]
+    pub wait_for: WaitFor,
+
+    /// Initial interval between polls of the node, in seconds
+    /// [default: from config, or `3`]
+    #[clap(long, global = true, value_parser = parse_non_negative)]
+    pub poll_interval: Option<f64>,
+
+    /// Factor the polling interval grows by after every poll
+    /// [default: from config, or `1`]
+    #[clap(long, global = true, value_parser = parse_non_negative)]
+    pub poll_backoff: Option<f64>,
+
+    /// Time after which waiting for a transaction is given up, in seconds
+    /// [default: from config, or `60`]
+    #[clap(long, global = true, value_parser = parse_non_negative)]
+    pub poll_timeout: Option<f64>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Deploy a contract, or all contracts of a manifest
+    Deploy {
+        /// Path to contract code
+        #[clap(short, long, required_unless_present = "manifest")]
+        contract_path: Option<PathBuf>,
+
+        /// Hexadecimal string of contract's owner [default: ``]
+        #[clap(short, long, default_value_t = String::from(""))]
+        owner: String,
+
+        /// Nonce [default: `0`]
+        #[clap(short, long, default_value_t = 0)]
+        nonce: u64,
+
+        /// Contract's constructor arguments
+        #[command(flatten)]
+        args: FnArgs,
+
+        /// Path to a TOML or JSON manifest listing contracts to deploy
+        #[clap(long, conflicts_with = "contract_path")]
+        manifest: Option<PathBuf>,
+
+        /// File deployment results of a manifest are written to
+        /// [default: manifest path with `results.json` extension]
+        #[clap(long, requires = "manifest")]
+        results_path: Option<PathBuf>,
+
+        /// Query method taking no arguments, run as a smoke test once the
+        /// contract is deployed
+        #[clap(long, conflicts_with = "manifest")]
+        verify_query: Option<String>,
+
+        /// Deploy with the next free nonce when the contract id is already
+        /// deployed, instead of skipping the deployment
+        #[clap(long)]
+        force: bool,
+    },
+
+    /// Call a contract method
+    Call {
+        /// Hexadecimal string of contract id
+        #[clap(short = 'i', long)]
+        contract_id: String,
+
+        /// Name of the contract method
+        #[clap(short = 'f', long)]
+        method: String,
+
+        /// Method's arguments
+        #[command(flatten)]
+        args: FnArgs,
+    },
+
+    /// Query a contract method, without spending funds
+    Query {
+        /// Hexadecimal string of contract id
+        #[clap(short = 'i', long)]
+        contract_id: String,
+
+        /// Name of the contract method
+        #[clap(short = 'f', long)]
+        method: String,
+
+        /// Method's arguments
+        #[command(flatten)]
+        args: FnArgs,
+
+        /// Format of the query result [default: `hex`]
+        #[clap(long, value_enum, default_value_t = QueryFormat::Hex)]
+        format: QueryFormat,
+
+        /// File the query result is written to when `file` format is used
+        #[clap(long)]
+        out_file: Option<PathBuf>,
+
+        /// Type description of the query result, used by `json` format
+        #[clap(long)]
+        result_type: Option<String>,
+    },
+
+    /// Show the balance of the funding account
+    Balance,
+
+    /// Transfer Dusk to a Phoenix public key or a Moonlight account
+    Transfer {
+        /// Base 58 string of the receiver's public key
+        #[clap(short = 't', long)]
+        receiver: String,
+
+        /// Amount to transfer, in Lux
+        #[clap(long)]
+        amount: u64,
+    },
+
+    /// Show the status of a transaction
+    TxStatus {
+        /// Hexadecimal string of transaction id
+        tx_id: String,
+    },
+
+    /// Export the chain state needed to sign transactions offline
+    Export {
+        /// Base 58 string of the Moonlight account public key
+        #[clap(long, conflicts_with = "view_key")]
+        address: Option<String>,
+
+        /// Base 58 string of the Phoenix view key
+        #[clap(long)]
+        view_key: Option<String>,
+
+        /// File the exported state is written to
+        #[clap(long)]
+        out_file: PathBuf,
+    },
+
+    /// Build and sign a transaction offline, from an exported state
+    Sign {
+        /// File containing the state exported by the `export` command
+        #[clap(long)]
+        state: PathBuf,
+
+        /// File the signed transaction is written to
+        #[clap(long)]
+        out_file: PathBuf,
+
+        #[command(subcommand)]
+        tx: TxCommand,
+    },
+
+    /// Estimate the gas spent by a deployment or a call, by simulating it
+    Estimate {
+        #[command(subcommand)]
+        tx: TxCommand,
+    },
+
+    /// Broadcast a transaction signed offline and wait for its outcome
+    Broadcast {
+        /// File containing the signed transaction
+        #[clap(value_name = "TX_FILE")]
+        signed_tx: PathBuf,
+    },
+
+    /// Show the public keys of the first profiles, without network access
+    Addresses {
+        /// Number of profiles shown [default: `1`]
+        #[clap(long, default_value_t = 1)]
+        count: u8,
+    },
+
+    /// Forget the notes cached for the scanned profiles and scan the
+    /// blockchain for them again
+    Rescan {
+        /// Block height scanning starts from
+        /// [default: keystore birthday, or `0`]
+        #[clap(long)]
+        from: Option<u64>,
+    },
+
+    /// Manage encrypted keystores
+    Keystore {
+        #[command(subcommand)]
+        command: KeystoreCommand,
+    },
+
+    /// Compute the ids contracts will be deployed at, without network access
+    ContractId {
+        /// Path to contract code
+        #[clap(short, long, required_unless_present = "manifest")]
+        contract_path: Option<PathBuf>,
+
+        /// Hexadecimal blake3 hash the contract code is checked against
+        #[clap(long, requires = "contract_path")]
+        bytecode_hash: Option<String>,
+
+        /// Hexadecimal string of contract's owner [default: ``]
+        #[clap(short, long, default_value_t = String::from(""))]
+        owner: String,
+
+        /// Comma separated nonces [default: `0`]
+        #[clap(short, long, value_delimiter = ',', default_value = "0")]
+        nonce: Vec<u64>,
+
+        /// Path to a TOML or JSON manifest listing contracts to deploy
+        #[clap(long, conflicts_with = "contract_path")]
+        manifest: Option<PathBuf>,
+    },
+
+    /// Search nonces giving a contract id matching a pattern, optionally
+    /// deploying the contract with the first nonce found
+    Vanity {
+        /// Path to contract code
+        #[clap(short, long)]
+        contract_path: PathBuf,
+
+        /// Hexadecimal string of contract's owner [default: ``]
+        #[clap(short, long, default_value_t = String::from(""))]
+        owner: String,
+
+        /// Hexadecimal digits the contract id starts with
+        #[clap(long, required_unless_present_any = ["suffix", "mask"])]
+        prefix: Option<String>,
+
+        /// Hexadecimal digits the contract id ends with
+        #[clap(long)]
+        suffix: Option<String>,
+
+        /// Hexadecimal digits from the start of the contract id, `_` matching
+        /// any digit
+        #[clap(long)]
+        mask: Option<String>,
+
+        /// First nonce tried [default: `0`]
+        #[clap(long, default_value_t = 0)]
+        start_nonce: u64,
+
+        /// Number of matching nonces searched for [default: `1`]
+        #[clap(long, default_value_t = 1)]
+        count: usize,
+
+        /// Maximum number of nonces tried [default: unbounded]
+        #[clap(long)]
+        max_iterations: Option<u64>,
+
+        /// Number of search threads [default: number of CPU cores]
+        #[clap(long)]
+        threads: Option<usize>,
+
+        /// Deploy the contract with the first nonce found
+        #[clap(long)]
+        deploy: bool,
+
+        /// Contract's constructor arguments, when deploying
+        #[command(flatten)]
+        args: FnArgs,
+    },
+}
+
+/// Keystore operations, passwords being read from `DUSK_KEYSTORE_PASSWORD`
+/// or prompted for
+#[derive(Subcommand, Debug)]
+pub enum KeystoreCommand {
+    /// Create a keystore with a new recovery phrase
+    Create {
+        /// Keystore file to create
+        path: PathBuf,
+
+        /// Block height scanning for notes starts from
+        /// [default: current block height]
+        #[clap(long)]
+        birthday: Option<u64>,
+    },
+
+    /// Create a keystore from the seed phrase or Moonlight secret key given
+    /// by `--seed` or `--moonlight`, or prompted for
+    Import {
+        /// Keystore file to create
+        path: PathBuf,
+
+        /// Block height scanning for notes starts from [default: `0`]
+        #[clap(long)]
+        birthday: Option<u64>,
+    },
+
+    /// Show the secret of a keystore
+    Export {
+        /// Keystore file to read
+        path: PathBuf,
+    },
+}
+
+/// Transactions which can be signed offline or estimated
+#[derive(Subcommand, Debug)]
+pub enum TxCommand {
+    /// Deploy a contract
+    Deploy {
+        /// Path to contract code
+        #[clap(short, long)]
+        contract_path: PathBuf,
+
+        /// Hexadecimal string of contract's owner [default: ``]
+        #[clap(short, long, default_value_t = String::from(""))]
+        owner: String,
+
+        /// Nonce [default: `0`]
+        #[clap(short, long, default_value_t = 0)]
+        nonce: u64,
+
+        /// Contract's constructor arguments
+        #[command(flatten)]
+        args: FnArgs,
+    },
+
+    /// Call a contract method
+    Call {
+        /// Hexadecimal string of contract id
+        #[clap(short = 'i', long)]
+        contract_id: String,
+
+        /// Name of the contract method
+        #[clap(short = 'f', long)]
+        method: String,
+
+        /// Method's arguments
+        #[command(flatten)]
+        args: FnArgs,
+    },
+}
+
+/// Arguments passed to a contract constructor or method, given either as
+/// hexadecimal rkyv serialization or as JSON value with a type description
+#[derive(clap::Args, Debug, Clone)]
+pub struct FnArgs {
+    /// Hexadecimal string of rkyv serialized arguments [default: ``]
     #[clap(short, long, default_value_t = String::from(""))]
-    pub moonlight: String,
+    pub args: String,
+
+    /// JSON value of arguments, encoded according to `--args-type`
+    #[clap(long, conflicts_with = "args", requires = "args_type")]
+    pub args_json: Option<String>,
+
+    /// Type description of arguments, e.g. `(u64, Vec<u8>, {owner: BlsPublicKey})`
+    #[clap(long, requires = "args_json")]
+    pub args_type: Option<String>,
+}
+
+/// Output format of query results
+#[derive(ValueEnum, Debug, Clone, Copy)]
+pub enum QueryFormat {
+    /// Hexadecimal string
+    Hex,
+    /// Base 64 string
+    Base64,
+    /// Raw bytes dumped to a file
+    File,
+    /// Pretty JSON decoded according to `--result-type`
+    Json,
+}
+
+/// Gas limit used when not given
+pub const DEFAULT_GAS_LIMIT: u64 = 500000000;
+
+/// Gas limit of a transaction, either given or estimated by simulating the
+/// transaction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GasLimit {
+    Auto,
+    Fixed(u64),
+}
+
+impl FromStr for GasLimit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(GasLimit::Auto),
+            _ => s
+                .parse()
+                .map(GasLimit::Fixed)
+                .map_err(|_| format!("expected a number or `auto`, found `{}`", s)),
+        }
+    }
+}
+
+impl fmt::Display for GasLimit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GasLimit::Auto => write!(f, "auto"),
+            GasLimit::Fixed(gas_limit) => write!(f, "{}", gas_limit),
+        }
+    }
+}
+
+/// What to wait for after propagating a transaction
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WaitFor {
+    /// Return as soon as the transaction is propagated
+    None,
+    /// Wait until the transaction is included in a block
+    #[default]
+    Included,
+    /// Wait until the given number of blocks follow the including block
+    Confirmations(u64),
+    /// Wait until the including block is final
+    Finalized,
+}
+
+impl FromStr for WaitFor {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(WaitFor::None),
+            "included" => Ok(WaitFor::Included),
+            "finalized" => Ok(WaitFor::Finalized),
+            _ => s.parse().map(WaitFor::Confirmations).map_err(|_| {
+                format!(
+                    "expected `none`, `included`, `finalized` or a number, found `{}`",
+                    s
+                )
+            }),
+        }
+    }
+}
+
+impl fmt::Display for WaitFor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WaitFor::None => write!(f, "none"),
+            WaitFor::Included => write!(f, "included"),
+            WaitFor::Confirmations(n) => write!(f, "{}", n),
+            WaitFor::Finalized => write!(f, "finalized"),
+        }
+    }
+}
+
+/// Parses a finite, non-negative number
+fn parse_non_negative(s: &str) -> Result<f64, String> {
+    let value = s
+        .parse()
+        .map_err(|_| format!("expected a number, found `{}`", s))?;
+    polling::non_negative(value)
+}
+
+/// Output format of command results
+#[derive(ValueEnum, Debug, Clone, Copy)]
+pub enum OutputFormat {
+    /// Human readable log lines on stderr
+    Text,
+    /// Single JSON object on stdout
+    Json,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wait_for_parsing() {
+        assert_eq!("none".parse(), Ok(WaitFor::None));
+        assert_eq!("included".parse(), Ok(WaitFor::Included));
+        assert_eq!("finalized".parse(), Ok(WaitFor::Finalized));
+        assert_eq!("0".parse(), Ok(WaitFor::Confirmations(0)));
+        assert_eq!("12".parse(), Ok(WaitFor::Confirmations(12)));
+        for s in ["", "Included", "-1", "1.5", "final"] {
+            assert!(s.parse::<WaitFor>().is_err(), "{}", s);
+        }
+        for wait_for in [
+            WaitFor::None,
+            WaitFor::Included,
+            WaitFor::Confirmations(3),
+            WaitFor::Finalized,
+        ] {
+            assert_eq!(wait_for.to_string().parse(), Ok(wait_for));
+        }
+    }
+
+    #[test]
+    fn gas_limit_parsing() {
+        assert_eq!("auto".parse(), Ok(GasLimit::Auto));
+        assert_eq!("0".parse(), Ok(GasLimit::Fixed(0)));
+        assert_eq!(
+            DEFAULT_GAS_LIMIT.to_string().parse(),
+            Ok(GasLimit::Fixed(DEFAULT_GAS_LIMIT))
+        );
+        assert_eq!(u64::MAX.to_string().parse(), Ok(GasLimit::Fixed(u64::MAX)));
+        for s in ["", "Auto", "-1", "1.5", "18446744073709551616"] {
+            assert!(s.parse::<GasLimit>().is_err(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn poll_values_parsing() {
+        assert_eq!(parse_non_negative("0"), Ok(0.0));
+        assert_eq!(parse_non_negative("2.5"), Ok(2.5));
+        for s in ["-1", "inf", "NaN", "1e400", "three"] {
+            assert!(parse_non_negative(s).is_err(), "{}", s);
+        }
+    }
 }
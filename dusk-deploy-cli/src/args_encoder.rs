@@ -0,0 +1,595 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use crate::type_desc::TypeDesc;
+use crate::Error;
+use dusk_bytes::DeserializableSlice;
+use execution_core::signatures::bls::PublicKey as BlsPublicKey;
+use execution_core::transfer::phoenix::PublicKey as PhoenixPublicKey;
+use serde_json::Value;
+use std::borrow::Cow;
+
+/// Maximum length of a string stored inline in its archived form
+const STRING_INLINE_CAPACITY: usize = 7;
+
+/// Positions of already written out-of-line data of a value
+enum Resolver {
+    Leaf,
+    Pos(usize),
+    Many(Vec<Resolver>),
+}
+
+/// Encodes JSON values into rkyv serializations of the types given by
+/// a [`TypeDesc`], producing the same bytes as `rkyv::to_bytes` would
+/// for the corresponding Rust value.
+pub struct ArgsEncoder {
+    buffer: Vec<u8>,
+}
+
+impl ArgsEncoder {
+    pub fn encode(desc: &TypeDesc, value: &Value) -> Result<Vec<u8>, Error> {
+        let mut encoder = ArgsEncoder { buffer: vec![] };
+        let layout = desc.layout()?;
+        let resolver = encoder.serialize(desc, value)?;
+        encoder.align(layout.align);
+        encoder.resolve_aligned(desc, value, resolver)?;
+        Ok(encoder.buffer)
+    }
+
+    fn align(&mut self, align: usize) -> usize {
+        let padding = (align - (self.buffer.len() % align)) % align;
+        self.buffer.resize(self.buffer.len() + padding, 0);
+        self.buffer.len()
+    }
+
+    /// Writes the archived form of a value at the current position,
+    /// which must already be aligned
+    fn resolve_aligned(
+        &mut self,
+        desc: &TypeDesc,
+        value: &Value,
+        resolver: Resolver,
+    ) -> Result<(), Error> {
+        let pos = self.buffer.len();
+        let mut out = vec![0u8; desc.layout()?.size];
+        resolve(desc, value, resolver, pos, &mut out)?;
+        self.buffer.extend_from_slice(&out);
+        Ok(())
+    }
+
+    /// Writes out-of-line data of a value (contents of strings and vectors),
+    /// depth first, in field declaration order for structs
+    fn serialize(&mut self, desc: &TypeDesc, value: &Value) -> Result<Resolver, Error> {
+        Ok(match desc {
+            TypeDesc::String => {
+                let s = as_str(value)?;
+                if s.len() <= STRING_INLINE_CAPACITY {
+                    Resolver::Leaf
+                } else {
+                    let pos = self.buffer.len();
+                    self.buffer.extend_from_slice(s.as_bytes());
+                    Resolver::Pos(pos)
+                }
+            }
+            TypeDesc::Vec(t) => {
+                let elements = elements(t, value)?;
+                let mut resolvers = Vec::with_capacity(elements.len());
+                for e in elements.iter() {
+                    resolvers.push(self.serialize(t, e)?);
+                }
+                let pos = self.align(t.layout()?.align);
+                for (e, r) in elements.iter().zip(resolvers) {
+                    self.resolve_aligned(t, e, r)?;
+                }
+                Resolver::Pos(pos)
+            }
+            TypeDesc::Array(t, n) => {
+                let elements = elements(t, value)?;
+                if elements.len() != *n {
+                    return Err(encoding_err(format!(
+                        "expected {} elements, found {}",
+                        n,
+                        elements.len()
+                    )));
+                }
+                Resolver::Many(
+                    elements
+                        .iter()
+                        .map(|e| self.serialize(t, e))
+                        .collect::<Result<_, _>>()?,
+                )
+            }
+            TypeDesc::Option(t) => match value {
+                Value::Null => Resolver::Leaf,
+                v => Resolver::Many(vec![self.serialize(t, v)?]),
+            },
+            TypeDesc::Tuple(types, _) => {
+                // rkyv serializes tuple elements from last to first
+                let values = tuple_values(types, value)?;
+                let mut resolvers = types
+                    .iter()
+                    .zip(values.iter())
+                    .rev()
+                    .map(|(t, v)| self.serialize(t, v))
+                    .collect::<Result<Vec<_>, _>>()?;
+                resolvers.reverse();
+                Resolver::Many(resolvers)
+            }
+            TypeDesc::Struct { fields, .. } => Resolver::Many(
+                fields
+                    .iter()
+                    .map(|(name, t)| self.serialize(t, field_value(name, value)?))
+                    .collect::<Result<_, _>>()?,
+            ),
+            _ => Resolver::Leaf,
+        })
+    }
+}
+
+/// Fills in the archived form of a value located at `pos`
+fn resolve(
+    desc: &TypeDesc,
+    value: &Value,
+    resolver: Resolver,
+    pos: usize,
+    out: &mut [u8],
+) -> Result<(), Error> {
+    match desc {
+        TypeDesc::U8 => write_uint(value, out, u8::MAX as u128)?,
+        TypeDesc::U16 => write_uint(value, out, u16::MAX as u128)?,
+        TypeDesc::U32 => write_uint(value, out, u32::MAX as u128)?,
+        TypeDesc::U64 => write_uint(value, out, u64::MAX as u128)?,
+        TypeDesc::U128 => write_uint(value, out, u128::MAX)?,
+        TypeDesc::I8 => write_int(value, out, i8::MIN as i128, i8::MAX as i128)?,
+        TypeDesc::I16 => write_int(value, out, i16::MIN as i128, i16::MAX as i128)?,
+        TypeDesc::I32 => write_int(value, out, i32::MIN as i128, i32::MAX as i128)?,
+        TypeDesc::I64 => write_int(value, out, i64::MIN as i128, i64::MAX as i128)?,
+        TypeDesc::I128 => write_int(value, out, i128::MIN, i128::MAX)?,
+        TypeDesc::Bool => {
+            out[0] = value
+                .as_bool()
+                .ok_or_else(|| encoding_err(format!("expected bool, found {}", value)))?
+                as u8
+        }
+        TypeDesc::String => {
+            let s = as_str(value)?;
+            match resolver {
+                Resolver::Pos(target) => {
+                    out[0..4].copy_from_slice(&(s.len() as u32).to_le_bytes());
+                    out[4..8].copy_from_slice(&rel_offset(pos, target).to_le_bytes());
+                }
+                _ => {
+                    out[..s.len()].copy_from_slice(s.as_bytes());
+                    out[STRING_INLINE_CAPACITY] = s.len() as u8;
+                }
+            }
+        }
+        TypeDesc::Vec(t) => {
+            let len = elements(t, value)?.len();
+            if let Resolver::Pos(target) = resolver {
+                out[0..4].copy_from_slice(&rel_offset(pos, target).to_le_bytes());
+                out[4..8].copy_from_slice(&(len as u32).to_le_bytes());
+            }
+        }
+        TypeDesc::BlsPublicKey => {
+            let pk = BlsPublicKey::from_slice(&key_bytes(value)?)?;
+            out.copy_from_slice(&archived_bytes(&pk, out.len())?);
+        }
+        TypeDesc::PhoenixPublicKey => {
+            let pk = PhoenixPublicKey::from_slice(&key_bytes(value)?)?;
+            out.copy_from_slice(&archived_bytes(&pk, out.len())?);
+        }
+        TypeDesc::Array(t, _) => {
+            let size = t.layout()?.size;
+            let elements = elements(t, value)?;
+            for (i, (e, r)) in elements.iter().zip(many(resolver)).enumerate() {
+                resolve(t, e, r, pos + i * size, &mut out[i * size..(i + 1) * size])?;
+            }
+        }
+        TypeDesc::Option(t) => {
+            if let Resolver::Many(mut resolvers) = resolver {
+                out[0] = 1;
+                let offset = TypeDesc::option_value_offset(t)?;
+                let size = t.layout()?.size;
+                resolve(
+                    t,
+                    value,
+                    resolvers.remove(0),
+                    pos + offset,
+                    &mut out[offset..offset + size],
+                )?;
+            }
+        }
+        TypeDesc::Tuple(types, _) => {
+            let values = tuple_values(types, value)?;
+            let offsets = desc.layout()?.offsets;
+            for (i, r) in many(resolver).into_iter().enumerate() {
+                let (offset, size) = (offsets[i], types[i].layout()?.size);
+                resolve(
+                    &types[i],
+                    &values[i],
+                    r,
+                    pos + offset,
+                    &mut out[offset..offset + size],
+                )?;
+            }
+        }
+        TypeDesc::Struct { fields, .. } => {
+            let offsets = desc.layout()?.offsets;
+            for (i, r) in many(resolver).into_iter().enumerate() {
+                let (name, t) = &fields[i];
+                let (offset, size) = (offsets[i], t.layout()?.size);
+                resolve(
+                    t,
+                    field_value(name, value)?,
+                    r,
+                    pos + offset,
+                    &mut out[offset..offset + size],
+                )?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn encoding_err(msg: impl Into<Cow<'static, str>>) -> Error {
+    Error::Encoding(msg.into())
+}
+
+fn many(resolver: Resolver) -> Vec<Resolver> {
+    match resolver {
+        Resolver::Many(resolvers) => resolvers,
+        _ => vec![],
+    }
+}
+
+/// Offset of a relative pointer located at `from`, pointing to `to`
+fn rel_offset(from: usize, to: usize) -> i32 {
+    (to as i64 - from as i64) as i32
+}
+
+fn as_str(value: &Value) -> Result<&str, Error> {
+    value
+        .as_str()
+        .ok_or_else(|| encoding_err(format!("expected string, found {}", value)))
+}
+
+/// Elements of a vector or array, byte sequences may also be given
+/// as hexadecimal strings
+fn elements<'a>(elem: &TypeDesc, value: &'a Value) -> Result<Cow<'a, [Value]>, Error> {
+    match value {
+        Value::Array(values) => Ok(Cow::Borrowed(values)),
+        Value::String(s) if *elem == TypeDesc::U8 => Ok(Cow::Owned(
+            hex::decode(s.trim_start_matches("0x"))?
+                .into_iter()
+                .map(Value::from)
+                .collect(),
+        )),
+        _ => Err(encoding_err(format!("expected array, found {}", value))),
+    }
+}
+
+/// Values of a tuple, unit may also be given as null
+fn tuple_values<'a>(types: &[TypeDesc], value: &'a Value) -> Result<Cow<'a, [Value]>, Error> {
+    let values = match value {
+        Value::Null if types.is_empty() => return Ok(Cow::Owned(vec![])),
+        Value::Array(values) => values,
+        _ => return Err(encoding_err(format!("expected array, found {}", value))),
+    };
+    if values.len() != types.len() {
+        return Err(encoding_err(format!(
+            "expected tuple of {} elements, found {}",
+            types.len(),
+            value
+        )));
+    }
+    Ok(Cow::Borrowed(values))
+}
+
+fn field_value<'a>(name: &str, value: &'a Value) -> Result<&'a Value, Error> {
+    value
+        .as_object()
+        .ok_or_else(|| encoding_err(format!("expected object, found {}", value)))?
+        .get(name)
+        .ok_or_else(|| encoding_err(format!("missing field '{}'", name)))
+}
+
+fn write_uint(value: &Value, out: &mut [u8], max: u128) -> Result<(), Error> {
+    let v = match value {
+        Value::Number(n) => n.as_u64().map(u128::from),
+        Value::String(s) => s.parse::<u128>().ok(),
+        _ => None,
+    }
+    .filter(|v| *v <= max)
+    .ok_or_else(|| {
+        encoding_err(format!(
+            "expected unsigned integer up to {}, found {}",
+            max, value
+        ))
+    })?;
+    out.copy_from_slice(&v.to_le_bytes()[..out.len()]);
+    Ok(())
+}
+
+fn write_int(value: &Value, out: &mut [u8], min: i128, max: i128) -> Result<(), Error> {
+    let v = match value {
+        Value::Number(n) => n.as_i64().map(i128::from),
+        Value::String(s) => s.parse::<i128>().ok(),
+        _ => None,
+    }
+    .filter(|v| *v >= min && *v <= max)
+    .ok_or_else(|| {
+        encoding_err(format!(
+            "expected integer between {} and {}, found {}",
+            min, max, value
+        ))
+    })?;
+    out.copy_from_slice(&v.to_le_bytes()[..out.len()]);
+    Ok(())
+}
+
+/// Public keys are given as base 58 strings, or as hexadecimal strings
+/// prefixed with `0x`
+fn key_bytes(value: &Value) -> Result<Vec<u8>, Error> {
+    let s = as_str(value)?;
+    match s.strip_prefix("0x") {
+        Some(hex) => Ok(hex::decode(hex)?),
+        None => Ok(bs58::decode(s).into_vec()?),
+    }
+}
+
+/// Archived form of a plain type, which contains no relative pointers
+/// and hence can be copied to any position
+fn archived_bytes<T>(value: &T, size: usize) -> Result<Vec<u8>, Error>
+where
+    T: rkyv::Serialize<rkyv::ser::serializers::AllocSerializer<256>>,
+{
+    let bytes = rkyv::to_bytes::<_, 256>(value).map_err(|_| Error::Rkyv)?;
+    Ok(bytes[bytes.len() - size..].to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rkyv::ser::serializers::AllocSerializer;
+    use serde_json::json;
+    use std::mem::MaybeUninit;
+    use std::ptr;
+
+    /// Offsets of the fields of an archived type, as laid out by rustc
+    macro_rules! offsets {
+        ($archived:ty, $($field:tt),+) => {{
+            let value = MaybeUninit::<$archived>::uninit();
+            let base = value.as_ptr();
+            // SAFETY: only addresses of fields are taken, no value is read
+            [$(unsafe { ptr::addr_of!((*base).$field) as usize - base as usize }),+]
+        }};
+    }
+
+    #[derive(rkyv::Archive, rkyv::Serialize)]
+    struct Inner {
+        flag: bool,
+        value: u32,
+        name: String,
+    }
+
+    #[derive(rkyv::Archive, rkyv::Serialize)]
+    struct Outer {
+        id: u64,
+        inner: Inner,
+        all: Vec<Inner>,
+        extra: Option<u16>,
+    }
+
+    #[derive(rkyv::Archive, rkyv::Serialize)]
+    #[archive_attr(repr(C))]
+    struct ReprC {
+        a: u8,
+        b: u64,
+        c: bool,
+    }
+
+    #[derive(rkyv::Archive, rkyv::Serialize)]
+    struct Pair {
+        x: String,
+        y: Vec<u8>,
+    }
+
+    fn inner_desc() -> String {
+        let [flag, value, name] = offsets!(ArchivedInner, flag, value, name);
+        format!(
+            "{{flag: bool @ {}, value: u32 @ {}, name: String @ {}}}",
+            flag, value, name
+        )
+    }
+
+    fn assert_encodes<T>(desc: &str, json: Value, value: &T)
+    where
+        T: rkyv::Serialize<AllocSerializer<256>>,
+    {
+        let desc: TypeDesc = desc.parse().unwrap();
+        let expected = rkyv::to_bytes::<_, 256>(value).unwrap();
+        assert_eq!(
+            ArgsEncoder::encode(&desc, &json).unwrap(),
+            expected.as_slice(),
+            "{} {}",
+            desc,
+            json
+        );
+    }
+
+    #[test]
+    fn scalars() {
+        assert_encodes("u8", json!(255), &255u8);
+        assert_encodes("u16", json!(513), &513u16);
+        assert_encodes("u32", json!(70000), &70000u32);
+        assert_encodes("u64", json!(u64::MAX), &u64::MAX);
+        assert_encodes("u128", json!(u128::MAX.to_string()), &u128::MAX);
+        assert_encodes("i8", json!(-128), &-128i8);
+        assert_encodes("i16", json!(-2), &-2i16);
+        assert_encodes("i32", json!(i32::MIN), &i32::MIN);
+        assert_encodes("i64", json!("-9"), &-9i64);
+        assert_encodes("i128", json!(i128::MIN.to_string()), &i128::MIN);
+        assert_encodes("bool", json!(true), &true);
+        assert_encodes("()", json!(null), &());
+    }
+
+    #[test]
+    fn strings() {
+        for s in [
+            "",
+            "abc",
+            "seven c",
+            "eight ch",
+            "a string stored out of line",
+        ] {
+            assert_encodes("String", json!(s), &String::from(s));
+        }
+        assert_encodes(
+            "(String, String)",
+            json!(["inline", "out of line"]),
+            &(String::from("inline"), String::from("out of line")),
+        );
+    }
+
+    #[test]
+    fn vectors_and_arrays() {
+        assert_encodes("Vec<u32>", json!([1, 2, 3]), &vec![1u32, 2, 3]);
+        assert_encodes("Vec<u64>", json!([]), &Vec::<u64>::new());
+        assert_encodes("Vec<u8>", json!("0x0102ff"), &vec![1u8, 2, 255]);
+        assert_encodes(
+            "Vec<String>",
+            json!(["a", "longer than seven"]),
+            &vec![String::from("a"), String::from("longer than seven")],
+        );
+        assert_encodes(
+            "Vec<Vec<u16>>",
+            json!([[1], [], [2, 3]]),
+            &vec![vec![1u16], vec![], vec![2, 3]],
+        );
+        assert_encodes("[u16; 3]", json!([1, 2, 3]), &[1u16, 2, 3]);
+        assert_encodes("ContractId", json!(hex::encode([7u8; 32])), &[7u8; 32]);
+        assert_encodes(
+            "[String; 2]",
+            json!(["out of line string", "x"]),
+            &[String::from("out of line string"), String::from("x")],
+        );
+    }
+
+    #[test]
+    fn options() {
+        assert_encodes("Option<u64>", json!(null), &None::<u64>);
+        assert_encodes("Option<u64>", json!(7), &Some(7u64));
+        assert_encodes("Option<u8>", json!(7), &Some(7u8));
+        assert_encodes(
+            "Option<String>",
+            json!("long enough to be out of line"),
+            &Some(String::from("long enough to be out of line")),
+        );
+        assert_encodes(
+            "Vec<Option<u32>>",
+            json!([1, null, 3]),
+            &vec![Some(1u32), None, Some(3)],
+        );
+    }
+
+    #[test]
+    fn tuples() {
+        assert_encodes("(u8, u64)", json!([1, 2]), &(1u8, 2u64));
+        assert_encodes("(u64, u8)", json!([1, 2]), &(1u64, 2u8));
+        assert_encodes("(u32,)", json!([5]), &(5u32,));
+        let [a, b, c] = offsets!(rkyv::Archived<(u8, u64, String)>, 0, 1, 2);
+        assert_encodes(
+            &format!("(u8 @ {}, u64 @ {}, String @ {})", a, b, c),
+            json!([1, 2, "a string stored out of line"]),
+            &(1u8, 2u64, String::from("a string stored out of line")),
+        );
+    }
+
+    #[test]
+    fn structs() {
+        assert_encodes(
+            "repr(C) {a: u8, b: u64, c: bool}",
+            json!({"a": 1, "b": 2, "c": true}),
+            &ReprC {
+                a: 1,
+                b: 2,
+                c: true,
+            },
+        );
+        assert_encodes(
+            "{x: String, y: Vec<u8>}",
+            json!({"x": "out of line string", "y": "0xff"}),
+            &Pair {
+                x: String::from("out of line string"),
+                y: vec![255],
+            },
+        );
+
+        let inner = |flag, value, name: &str| Inner {
+            flag,
+            value,
+            name: String::from(name),
+        };
+        let [id, inner_offset, all, extra] = offsets!(ArchivedOuter, id, inner, all, extra);
+        let desc = format!(
+            "{{id: u64 @ {}, inner: {} @ {}, all: Vec<{}> @ {}, extra: Option<u16> @ {}}}",
+            id,
+            inner_desc(),
+            inner_offset,
+            inner_desc(),
+            all,
+            extra
+        );
+        assert_encodes(
+            &desc,
+            json!({
+                "id": 1,
+                "inner": {"flag": true, "value": 2, "name": "inner name, out of line"},
+                "all": [
+                    {"flag": false, "value": 3, "name": "a"},
+                    {"flag": true, "value": 4, "name": "another out of line name"}
+                ],
+                "extra": 5
+            }),
+            &Outer {
+                id: 1,
+                inner: inner(true, 2, "inner name, out of line"),
+                all: vec![
+                    inner(false, 3, "a"),
+                    inner(true, 4, "another out of line name"),
+                ],
+                extra: Some(5),
+            },
+        );
+    }
+
+    #[test]
+    fn invalid_values() {
+        for (desc, value) in [
+            ("u8", json!(256)),
+            ("i8", json!(-129)),
+            ("u64", json!(-1)),
+            ("u128", json!("-1")),
+            ("bool", json!(1)),
+            ("String", json!(1)),
+            ("[u8; 2]", json!([1])),
+            ("Vec<u8>", json!("0xzz")),
+            ("(u8, u8)", json!([1])),
+            ("{a: u8}", json!({"b": 1})),
+            ("{a: u8}", json!([1])),
+            ("BlsPublicKey", json!("not base 58")),
+        ] {
+            let desc: TypeDesc = desc.parse().unwrap();
+            assert!(
+                ArgsEncoder::encode(&desc, &value).is_err(),
+                "{} {}",
+                desc,
+                value
+            );
+        }
+    }
+}
@@ -0,0 +1,1327 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use crate::args::{
+    FnArgs, GasLimit, GlobalArgs, QueryFormat, TxCommand, WaitFor, DEFAULT_GAS_LIMIT,
+};
+use crate::args_encoder::ArgsEncoder;
+use crate::block::Block;
+use crate::config::BlockchainAccessConfig;
+use crate::dcli_prover_client::{tx_id, DCliProverClient, Propagation};
+use crate::dcli_state_client::DCliStateClient;
+use crate::dcli_store::DCliStore;
+use crate::executor::{bytecode_hash, Executor, TxOutcome};
+use crate::gen_id::{gen_contract_id, ContractIdGenerator};
+use crate::keystore::{self, Keystore, KeystoreFile, SecretKind};
+use crate::manifest::{write_results, Manifest, References};
+use crate::offline::{self, OfflineState};
+use crate::output::{
+    AddressesReport, BalanceReport, BroadcastReport, CallReport, ContractIdEntry, ContractIdReport,
+    DeployReport, DeployReports, EstimateReport, ExportReport, Funding, KeystoreReport,
+    ManifestReport, QueryReport, Report, RescanReport, SignReport, TransferReport, TxStatusReport,
+    VanityMatch, VanityReport,
+};
+use crate::polling::WaitPolicy;
+use crate::progress::ProgressDisplay;
+use crate::result_decoder::ResultDecoder;
+use crate::type_desc::TypeDesc;
+use crate::vanity::{Pattern, Vanity};
+use crate::verifier::Verifier;
+use crate::wallet_builder::WalletBuilder;
+use crate::Error;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use bip39::{Language, Mnemonic, MnemonicType};
+use dusk_bytes::{DeserializableSlice, Serializable};
+use execution_core::signatures::bls::PublicKey as BlsPublicKey;
+use execution_core::transfer::phoenix::{PublicKey, ViewKey};
+use execution_core::transfer::Transaction;
+use rand::prelude::*;
+use rand::rngs::StdRng;
+use rusk_http_client::{BlockchainInquirer, ContractId, ContractInquirer, RuskHttpClient};
+use std::cmp::min;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+use wallet::{StateClient, Store, Wallet};
+
+/// Configuration and global options shared by all commands
+pub struct Context {
+    pub config: BlockchainAccessConfig,
+    pub global: GlobalArgs,
+    keystore: Option<KeystoreFile>,
+}
+
+impl Context {
+    pub fn new(config: BlockchainAccessConfig, global: GlobalArgs) -> Result<Self, Error> {
+        let keystore = match &global.keystore {
+            Some(_) if !global.seed.is_empty() || !global.moonlight.is_empty() => {
+                return Err(Error::InvalidArgument(
+                    "--keystore cannot be used with --seed or --moonlight".into(),
+                ))
+            }
+            Some(path) => Some(KeystoreFile::load(path)?),
+            None => None,
+        };
+        Ok(Self {
+            config,
+            global,
+            keystore,
+        })
+    }
+
+    /// Returns the index of the Phoenix key or Moonlight account funding
+    /// transactions
+    pub fn profile_index(&self) -> u64 {
+        self.global.profile_index as u64
+    }
+
+    /// Returns true if transactions are to be funded from a Moonlight account
+    pub fn moonlight(&self) -> bool {
+        match (self.global.funding, &self.keystore) {
+            (Some(funding), _) => funding == Funding::Moonlight,
+            (None, Some(keystore)) => keystore.kind() == SecretKind::Moonlight,
+            (None, None) => !self.global.moonlight.is_empty(),
+        }
+    }
+
+    pub fn client(&self) -> RuskHttpClient {
+        RuskHttpClient::new(self.config.rusk_address.clone())
+    }
+
+    /// Returns the store of the keys given by the global options
+    fn store(&self) -> Result<DCliStore, Error> {
+        match &self.keystore {
+            Some(file) => file.unlock(&keystore::password(false)?),
+            None if !self.global.moonlight.is_empty() => {
+                DCliStore::from_moonlight_key(&self.global.moonlight)
+            }
+            None => DCliStore::from_phrase(&self.global.seed),
+        }
+    }
+
+    /// Returns the block height scanning for notes starts from, taking care
+    /// of the relative starting block height, and by default of the birthday
+    /// of the keystore
+    fn start_block_height(&self) -> u64 {
+        scan_start_height(
+            self.global.block_height,
+            self.global.relative_height,
+            || {
+                // nothing is scanned for Moonlight accounts
+                if self.moonlight() {
+                    return None;
+                }
+                BlockchainInquirer::block_height(&self.client()).wait().ok()
+            },
+            self.birthday(),
+        )
+    }
+
+    /// Returns the block height recorded at the creation of the keystore
+    fn birthday(&self) -> Option<u64> {
+        self.keystore.as_ref().and_then(KeystoreFile::birthday)
+    }
+
+    /// Returns the view keys of the profiles whose notes are scanned for
+    /// together, the first `--scan-profiles` ones and the funding one
+    fn view_keys(&self, store: &DCliStore) -> Result<Vec<ViewKey>, Error> {
+        if self.moonlight() {
+            return Ok(vec![]);
+        }
+        let mut indices: Vec<u64> = (0..self.global.scan_profiles as u64).collect();
+        if !indices.contains(&self.profile_index()) {
+            indices.push(self.profile_index());
+        }
+        indices
+            .into_iter()
+            .map(|index| Ok(ViewKey::from(&store.fetch_secret_key(index)?)))
+            .collect()
+    }
+
+    /// Returns the directory notes are cached in, by default in the home
+    /// directory
+    fn cache_dir(&self) -> Option<PathBuf> {
+        self.config.cache_dir.clone().or_else(|| {
+            std::env::var_os("HOME")
+                .map(|home| PathBuf::from(home).join(".dusk-deploy-cli").join("cache"))
+        })
+    }
+
+    /// Returns the wait policy, polling settings given on the command line
+    /// overriding the configured ones
+    fn wait_policy(&self) -> WaitPolicy {
+        let mut polling = self.config.polling;
+        if let Some(interval) = self.global.poll_interval {
+            polling.interval = interval;
+        }
+        if let Some(backoff) = self.global.poll_backoff {
+            polling.backoff = backoff;
+        }
+        if let Some(timeout) = self.global.poll_timeout {
+            polling.timeout = timeout;
+        }
+        WaitPolicy {
+            wait_for: self.global.wait_for,
+            polling,
+        }
+    }
+
+    fn propagation(&self) -> Propagation {
+        if self.global.dry_run {
+            Propagation::DryRun
+        } else {
+            Propagation::Full
+        }
+    }
+
+    /// Builds a wallet from the global options
+    pub fn wallet(&self) -> Result<Wallet<DCliStore, DCliStateClient, DCliProverClient>, Error> {
+        self.wallet_scanning_from(self.store()?, self.start_block_height())
+    }
+
+    /// Builds a wallet scanning for notes from the given block height
+    fn wallet_scanning_from(
+        &self,
+        store: DCliStore,
+        start_block_height: u64,
+    ) -> Result<Wallet<DCliStore, DCliStateClient, DCliProverClient>, Error> {
+        let view_keys = self.view_keys(&store)?;
+        WalletBuilder::build(
+            self.config.rusk_address.clone(),
+            self.config.prover_address.clone(),
+            store,
+            start_block_height,
+            self.cache_dir(),
+            view_keys,
+            Some(ProgressDisplay::callback()),
+            self.propagation(),
+            self.wait_policy(),
+        )
+    }
+
+    /// Builds a wallet signing transactions offline from an exported state
+    pub fn offline_wallet(
+        &self,
+        state: OfflineState,
+    ) -> Result<Wallet<DCliStore, DCliStateClient, DCliProverClient>, Error> {
+        WalletBuilder::build_offline(self.store()?, state)
+    }
+
+    pub fn prover_client(&self) -> DCliProverClient {
+        DCliProverClient::new(
+            self.client(),
+            RuskHttpClient::new(self.config.prover_address.clone()),
+            self.propagation(),
+            self.wait_policy(),
+        )
+    }
+
+    pub fn funding(&self) -> Funding {
+        if self.moonlight() {
+            Funding::Moonlight
+        } else {
+            Funding::Phoenix
+        }
+    }
+
+    /// Returns the base 58 public key transactions are funded from
+    pub fn sender(
+        &self,
+        wallet: &Wallet<DCliStore, DCliStateClient, DCliProverClient>,
+    ) -> Result<String, Error> {
+        let bytes = if self.moonlight() {
+            wallet
+                .account_public_key(self.profile_index())?
+                .to_bytes()
+                .to_vec()
+        } else {
+            wallet.public_key(self.profile_index())?.to_bytes().to_vec()
+        };
+        Ok(bs58::encode(bytes).into_string())
+    }
+
+    /// Returns the base 58 public key transactions are funded from, derived
+    /// from the keys without building a wallet
+    fn store_sender(&self, store: &DCliStore) -> Result<String, Error> {
+        let bytes = if self.moonlight() {
+            BlsPublicKey::from(&store.fetch_account_secret_key(self.profile_index())?)
+                .to_bytes()
+                .to_vec()
+        } else {
+            PublicKey::from(&store.fetch_secret_key(self.profile_index())?)
+                .to_bytes()
+                .to_vec()
+        };
+        Ok(bs58::encode(bytes).into_string())
+    }
+
+    /// Writes the serialized transaction to the transaction file, if one is
+    /// given, in dry run mode
+    pub fn write_tx(&self, tx: &Transaction) -> Result<Option<PathBuf>, Error> {
+        match (&self.global.tx_file, self.global.dry_run) {
+            (Some(tx_file), true) => {
+                std::fs::write(tx_file, tx.to_var_bytes())?;
+                Ok(Some(tx_file.clone()))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Returns the nonce to deploy a contract with, or `None` if the
+    /// contract is already deployed. When forced, nonces following the given
+    /// one are tried until a contract id not yet deployed is found.
+    ///
+    /// When the existence check fails, e.g. on a response of the node not
+    /// recognised, a warning is logged and the contract is deployed.
+    pub fn free_nonce(
+        &self,
+        bytecode: &[u8],
+        owner: &[u8],
+        nonce: u64,
+        force: bool,
+    ) -> Result<Option<u64>, Error> {
+        let client = self.client();
+        let mut nonce = nonce;
+        loop {
+            let contract_id = gen_contract_id(bytecode, nonce, owner);
+            match ContractInquirer::contract_exists(&client, contract_id).wait() {
+                Ok(true) => {}
+                Ok(false) => return Ok(Some(nonce)),
+                Err(e) => {
+                    warn!(
+                        "Cannot check whether contract {} is already deployed: {}",
+                        hex::encode(contract_id),
+                        e
+                    );
+                    return Ok(Some(nonce));
+                }
+            }
+            if !force {
+                return Ok(None);
+            }
+            info!(
+                "Contract {} already deployed, trying next nonce",
+                hex::encode(contract_id)
+            );
+            nonce = nonce
+                .checked_add(1)
+                .ok_or(Error::InvalidArgument("no free nonce left".into()))?;
+        }
+    }
+
+    /// Reports a contract already deployed, without sending a transaction
+    pub fn already_deployed(
+        &self,
+        sender: String,
+        bytecode: &[u8],
+        owner: &[u8],
+        nonce: u64,
+    ) -> Result<DeployReport, Error> {
+        Ok(DeployReport {
+            contract_id: hex::encode(gen_contract_id(bytecode, nonce, owner)),
+            nonce,
+            tx_id: String::new(),
+            receipt: None,
+            funding: self.funding(),
+            sender,
+            bytecode_hash: hex::encode(bytecode_hash(bytecode)),
+            verification: None,
+            already_deployed: true,
+            dry_run: self.global.dry_run,
+            tx_file: None,
+        })
+    }
+
+    /// Deploys a contract, funded according to the global options
+    #[allow(clippy::too_many_arguments)]
+    pub fn deploy(
+        &self,
+        wallet: &Wallet<DCliStore, DCliStateClient, DCliProverClient>,
+        bytecode: &Vec<u8>,
+        owner: &[u8],
+        init_args: Option<Vec<u8>>,
+        nonce: u64,
+        gas_limit: GasLimit,
+        gas_price: u64,
+        smoke_test: Option<&str>,
+    ) -> Result<DeployReport, Error> {
+        let gas_limit = self.gas_limit(wallet, gas_limit, |gas_limit| {
+            self.execute_deploy(
+                wallet,
+                bytecode,
+                owner,
+                init_args.clone(),
+                nonce,
+                gas_limit,
+                gas_price,
+            )
+        })?;
+        let outcome = self.execute_deploy(
+            wallet, bytecode, owner, init_args, nonce, gas_limit, gas_price,
+        )?;
+
+        let contract_id = gen_contract_id(bytecode, nonce, owner);
+        let verification = match &outcome.receipt {
+            Some(receipt)
+                if receipt.error.is_none()
+                    && wallet.prover().propagation() == Propagation::Full =>
+            {
+                Some(Verifier::verify(&self.client(), contract_id, smoke_test)?)
+            }
+            _ => None,
+        };
+
+        Ok(DeployReport {
+            contract_id: hex::encode(contract_id),
+            nonce,
+            tx_id: outcome.tx_id,
+            receipt: outcome.receipt,
+            funding: self.funding(),
+            sender: self.sender(wallet)?,
+            bytecode_hash: hex::encode(bytecode_hash(bytecode)),
+            verification,
+            already_deployed: false,
+            dry_run: self.global.dry_run,
+            tx_file: self.write_tx(&outcome.tx)?,
+        })
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn execute_deploy(
+        &self,
+        wallet: &Wallet<DCliStore, DCliStateClient, DCliProverClient>,
+        bytecode: &Vec<u8>,
+        owner: &[u8],
+        init_args: Option<Vec<u8>>,
+        nonce: u64,
+        gas_limit: u64,
+        gas_price: u64,
+    ) -> Result<TxOutcome, Error> {
+        if self.moonlight() {
+            Executor::deploy_via_moonlight(
+                wallet,
+                bytecode,
+                owner,
+                init_args,
+                nonce,
+                self.profile_index(),
+                gas_limit,
+                gas_price,
+            )
+        } else {
+            Executor::deploy_via_phoenix(
+                wallet,
+                bytecode,
+                owner,
+                init_args,
+                nonce,
+                self.profile_index(),
+                gas_limit,
+                gas_price,
+            )
+        }
+    }
+
+    fn execute_call(
+        &self,
+        wallet: &Wallet<DCliStore, DCliStateClient, DCliProverClient>,
+        contract_id: &ContractId,
+        method: &str,
+        args: Vec<u8>,
+        gas_limit: u64,
+        gas_price: u64,
+    ) -> Result<TxOutcome, Error> {
+        if self.moonlight() {
+            Executor::call_via_moonlight(
+                wallet,
+                contract_id,
+                method,
+                args,
+                self.profile_index(),
+                gas_limit,
+                gas_price,
+            )
+        } else {
+            Executor::call_via_phoenix(
+                wallet,
+                contract_id,
+                method,
+                args,
+                self.profile_index(),
+                gas_limit,
+                gas_price,
+            )
+        }
+    }
+
+    /// Executes a deployment or a call given by a transaction command
+    fn execute_tx(
+        &self,
+        wallet: &Wallet<DCliStore, DCliStateClient, DCliProverClient>,
+        tx: &TxCommand,
+        gas_limit: u64,
+    ) -> Result<(TxOutcome, Option<String>), Error> {
+        let gas_price = self.global.gas_price;
+        match tx {
+            TxCommand::Deploy {
+                contract_path,
+                owner,
+                nonce,
+                args,
+            } => {
+                let bytecode = std::fs::read(contract_path)?;
+                let owner = hex::decode(owner)?;
+                let init_args = Some(encode_args(args)?).filter(|v| !v.is_empty());
+                let contract_id = hex::encode(gen_contract_id(&bytecode, *nonce, &owner));
+                let outcome = self.execute_deploy(
+                    wallet, &bytecode, &owner, init_args, *nonce, gas_limit, gas_price,
+                )?;
+                Ok((outcome, Some(contract_id)))
+            }
+            TxCommand::Call {
+                contract_id,
+                method,
+                args,
+            } => {
+                let contract_id = parse_contract_id(contract_id)?;
+                let args = encode_args(args)?;
+                let outcome =
+                    self.execute_call(wallet, &contract_id, method, args, gas_limit, gas_price)?;
+                Ok((outcome, None))
+            }
+        }
+    }
+
+    /// Returns the given gas limit or, when automatic, estimates it by
+    /// simulating the transaction executed by `execute`
+    fn gas_limit(
+        &self,
+        wallet: &Wallet<DCliStore, DCliStateClient, DCliProverClient>,
+        gas_limit: GasLimit,
+        execute: impl FnOnce(u64) -> Result<TxOutcome, Error>,
+    ) -> Result<u64, Error> {
+        match gas_limit {
+            GasLimit::Fixed(gas_limit) => Ok(gas_limit),
+            GasLimit::Auto => {
+                let outcome = wallet.prover().simulating(|| execute(DEFAULT_GAS_LIMIT))?;
+                let receipt = outcome.receipt.unwrap_or_default();
+                if let Some(err) = receipt.error {
+                    return Err(Error::Estimate(err.into()));
+                }
+                let gas_spent = receipt.gas_spent;
+                let gas_limit = self.recommended_gas_limit(gas_spent);
+                info!(
+                    "Estimated gas spent: {}, using gas limit {}",
+                    gas_spent, gas_limit
+                );
+                Ok(gas_limit)
+            }
+        }
+    }
+
+    /// Returns the gas spent increased by the safety margin
+    fn recommended_gas_limit(&self, gas_spent: u64) -> u64 {
+        gas_spent.saturating_mul(100 + self.global.gas_margin) / 100
+    }
+
+    /// Returns the given gas limit, failing when it is to be estimated
+    fn fixed_gas_limit(&self, context: &str) -> Result<u64, Error> {
+        match self.global.gas_limit {
+            GasLimit::Fixed(gas_limit) => Ok(gas_limit),
+            GasLimit::Auto => Err(Error::InvalidArgument(
+                format!("gas limit cannot be estimated {}", context).into(),
+            )),
+        }
+    }
+
+    /// Gas limit a simulation runs with, high enough for any transaction
+    /// unless given
+    fn simulation_gas_limit(&self) -> u64 {
+        match self.global.gas_limit {
+            GasLimit::Fixed(gas_limit) => gas_limit,
+            GasLimit::Auto => DEFAULT_GAS_LIMIT,
+        }
+    }
+}
+
+pub struct Commands;
+
+impl Commands {
+    pub fn deploy(
+        ctx: &Context,
+        contract_path: &Path,
+        owner: String,
+        nonce: u64,
+        args: FnArgs,
+        verify_query: Option<String>,
+        force: bool,
+    ) -> Result<DeployReport, Error> {
+        let mut bytecode_file = File::open(contract_path)?;
+        let mut bytecode = Vec::new();
+        bytecode_file.read_to_end(&mut bytecode)?;
+
+        let mut constructor_args: Option<Vec<u8>> = None;
+        let v = encode_args(&args)?;
+        if !v.is_empty() {
+            constructor_args = Some(v);
+        }
+
+        let owner = hex::decode(owner)?;
+
+        // checked before building the wallet, which scans for notes
+        let Some(free_nonce) = ctx.free_nonce(&bytecode, &owner, nonce, force)? else {
+            let sender = ctx.store_sender(&ctx.store()?)?;
+            return ctx.already_deployed(sender, &bytecode, &owner, nonce);
+        };
+
+        let wallet = ctx.wallet()?;
+
+        ctx.deploy(
+            &wallet,
+            &bytecode,
+            &owner,
+            constructor_args,
+            free_nonce,
+            ctx.global.gas_limit,
+            ctx.global.gas_price,
+            verify_query.as_deref(),
+        )
+    }
+
+    /// Deploys all contracts of a manifest in dependency order, with a single
+    /// wallet, stopping at the first failure. Contracts already deployed are
+    /// skipped, or deployed with the next free nonce when forced.
+    pub fn deploy_manifest(
+        ctx: &Context,
+        manifest_path: &Path,
+        results_path: Option<PathBuf>,
+        force: bool,
+    ) -> Result<ManifestReport, Error> {
+        if ctx.global.tx_file.is_some() {
+            return Err(Error::InvalidArgument(
+                "--tx-file cannot be used with a manifest".into(),
+            ));
+        }
+        let manifest = Manifest::load(manifest_path)?;
+        let results_path =
+            results_path.unwrap_or_else(|| manifest_path.with_extension("results.json"));
+
+        // references are resolved before deploying anything, so that
+        // invalid entries are detected without spending gas
+        let mut refs = References::default();
+        let mut deployments = vec![];
+        for entry in manifest.ordered()? {
+            let contract_path = manifest.contract_path(entry);
+            let bytecode = std::fs::read(&contract_path)?;
+            let owner = entry.owner(&refs)?;
+            let init_args = entry.init_args(&refs)?;
+            let free_nonce = ctx.free_nonce(&bytecode, &owner, entry.nonce, force)?;
+            let nonce = free_nonce.unwrap_or(entry.nonce);
+            let contract_id = gen_contract_id(&bytecode, nonce, &owner);
+            refs.insert(&entry.name, contract_id, owner.clone());
+            deployments.push((entry, contract_path, bytecode, owner, init_args, free_nonce));
+        }
+
+        let wallet = ctx.wallet()?;
+        let sender = ctx.sender(&wallet)?;
+
+        let mut contracts = DeployReports::new();
+        for (entry, contract_path, bytecode, owner, init_args, free_nonce) in deployments {
+            let Some(nonce) = free_nonce else {
+                let report =
+                    ctx.already_deployed(sender.clone(), &bytecode, &owner, entry.nonce)?;
+                contracts.insert(entry.name.clone(), report);
+                write_results(&results_path, &contracts)?;
+                continue;
+            };
+
+            info!("Deploying {} from {:?}", entry.name, contract_path);
+
+            let report = ctx.deploy(
+                &wallet,
+                &bytecode,
+                &owner,
+                init_args,
+                nonce,
+                entry
+                    .gas_limit
+                    .map(GasLimit::Fixed)
+                    .unwrap_or(ctx.global.gas_limit),
+                entry.gas_price.unwrap_or(ctx.global.gas_price),
+                entry.verify_query.as_deref(),
+            )?;
+
+            let failed = report.error().is_some();
+            contracts.insert(entry.name.clone(), report);
+            write_results(&results_path, &contracts)?;
+
+            if failed {
+                break;
+            }
+        }
+
+        Ok(ManifestReport {
+            results_path,
+            contracts,
+        })
+    }
+
+    pub fn call(
+        ctx: &Context,
+        contract_id: String,
+        method: String,
+        args: FnArgs,
+    ) -> Result<CallReport, Error> {
+        let contract_id_hex = contract_id;
+        let contract_id = parse_contract_id(&contract_id_hex)?;
+        let args = encode_args(&args)?;
+
+        let wallet = ctx.wallet()?;
+
+        let gas_limit = ctx.gas_limit(&wallet, ctx.global.gas_limit, |gas_limit| {
+            ctx.execute_call(
+                &wallet,
+                &contract_id,
+                &method,
+                args.clone(),
+                gas_limit,
+                ctx.global.gas_price,
+            )
+        })?;
+        let outcome = ctx.execute_call(
+            &wallet,
+            &contract_id,
+            &method,
+            args,
+            gas_limit,
+            ctx.global.gas_price,
+        )?;
+
+        Ok(CallReport {
+            contract_id: contract_id_hex,
+            method,
+            tx_id: outcome.tx_id,
+            receipt: outcome.receipt,
+            funding: ctx.funding(),
+            sender: ctx.sender(&wallet)?,
+            dry_run: ctx.global.dry_run,
+            tx_file: ctx.write_tx(&outcome.tx)?,
+        })
+    }
+
+    pub fn query(
+        ctx: &Context,
+        contract_id: String,
+        method: String,
+        args: FnArgs,
+        format: QueryFormat,
+        out_file: Option<PathBuf>,
+        result_type: Option<String>,
+    ) -> Result<QueryReport, Error> {
+        let contract_id_hex = contract_id;
+        let contract_id = parse_contract_id(&contract_id_hex)?;
+        let args = encode_args(&args)?;
+
+        let response =
+            ContractInquirer::query_contract_raw(&ctx.client(), args, contract_id, &method)
+                .wait()?;
+
+        let mut report = QueryReport {
+            contract_id: contract_id_hex,
+            method,
+            result: None,
+            out_file: None,
+            size: response.len(),
+        };
+        match format {
+            QueryFormat::Hex => report.result = Some(hex::encode(response).into()),
+            QueryFormat::Base64 => report.result = Some(BASE64.encode(response).into()),
+            QueryFormat::File => {
+                let out_file = out_file.ok_or(Error::InvalidArgument(
+                    "--out-file is required for file format".into(),
+                ))?;
+                std::fs::write(&out_file, &response)?;
+                report.out_file = Some(out_file);
+            }
+            QueryFormat::Json => {
+                let desc: TypeDesc = result_type
+                    .ok_or(Error::InvalidArgument(
+                        "--result-type is required for json format".into(),
+                    ))?
+                    .parse()?;
+                report.result = Some(ResultDecoder::decode(&desc, &response)?);
+            }
+        }
+
+        Ok(report)
+    }
+
+    pub fn balance(ctx: &Context) -> Result<BalanceReport, Error> {
+        let wallet = ctx.wallet()?;
+
+        let address = ctx.sender(&wallet)?;
+        let report = if ctx.moonlight() {
+            let account = wallet.get_account(ctx.profile_index())?;
+            BalanceReport {
+                funding: Funding::Moonlight,
+                address,
+                balance: account.balance,
+                spendable: None,
+                nonce: Some(account.nonce),
+            }
+        } else {
+            let balance = wallet.get_balance(ctx.profile_index())?;
+            BalanceReport {
+                funding: Funding::Phoenix,
+                address,
+                balance: balance.value,
+                spendable: Some(balance.spendable),
+                nonce: None,
+            }
+        };
+
+        Ok(report)
+    }
+
+    /// Forgets the notes cached for the Phoenix keys of the profiles scanned
+    /// for, and scans for them again from the given height
+    pub fn rescan(ctx: &Context, from: Option<u64>) -> Result<RescanReport, Error> {
+        if ctx.moonlight() {
+            return Err(Error::InvalidArgument(
+                "rescan applies to Phoenix notes, not to Moonlight accounts".into(),
+            ));
+        }
+        let from_height = from.unwrap_or_else(|| ctx.birthday().unwrap_or_default());
+        let store = ctx.store()?;
+        let vk = ViewKey::from(&store.fetch_secret_key(ctx.profile_index())?);
+
+        let state_client =
+            DCliStateClient::new(ctx.client(), from_height).with_cache_dir(ctx.cache_dir());
+        for vk in ctx.view_keys(&store)? {
+            state_client.discard_cache(&vk)?;
+        }
+
+        // the balance is computed from the notes found by a single scan
+        let wallet = ctx.wallet_scanning_from(store, from_height)?;
+        let balance = wallet.get_balance(ctx.profile_index())?;
+        Ok(RescanReport {
+            address: ctx.sender(&wallet)?,
+            from_height,
+            notes: wallet.state().cached_notes(&vk)?.len(),
+            balance: balance.value,
+            spendable: balance.spendable,
+        })
+    }
+
+    pub fn transfer(ctx: &Context, receiver: String, amount: u64) -> Result<TransferReport, Error> {
+        let receiver_bytes = bs58::decode(&receiver).into_vec()?;
+
+        let gas_limit = ctx.fixed_gas_limit("for transfers")?;
+
+        let wallet = ctx.wallet()?;
+
+        let tx = if ctx.moonlight() {
+            let receiver_pk = BlsPublicKey::from_slice(&receiver_bytes)?;
+            wallet.moonlight_transfer(
+                ctx.profile_index(),
+                &receiver_pk,
+                amount,
+                gas_limit,
+                ctx.global.gas_price,
+            )?
+        } else {
+            let receiver_pk = PublicKey::from_slice(&receiver_bytes)?;
+            let mut rng = StdRng::from_entropy();
+            wallet.phoenix_transfer(
+                &mut rng,
+                ctx.profile_index(),
+                &receiver_pk,
+                amount,
+                gas_limit,
+                ctx.global.gas_price,
+            )?
+        };
+
+        let outcome = Executor::outcome(&wallet, &tx)?;
+        Ok(TransferReport {
+            tx_id: outcome.tx_id,
+            receipt: outcome.receipt,
+            funding: ctx.funding(),
+            sender: ctx.sender(&wallet)?,
+            receiver,
+            amount,
+            dry_run: ctx.global.dry_run,
+            tx_file: ctx.write_tx(&tx)?,
+        })
+    }
+
+    pub fn tx_status(ctx: &Context, tx_id: String) -> Result<TxStatusReport, Error> {
+        let receipt = Executor::receipt(&ctx.client(), &tx_id)?;
+        Ok(TxStatusReport { tx_id, receipt })
+    }
+
+    /// Exports the chain state needed to sign transactions offline, for a
+    /// Moonlight account or the notes of a Phoenix view key
+    pub fn export(
+        ctx: &Context,
+        address: Option<String>,
+        view_key: Option<String>,
+        out_file: PathBuf,
+    ) -> Result<ExportReport, Error> {
+        let state_client = DCliStateClient::new(ctx.client(), ctx.start_block_height())
+            .with_cache_dir(ctx.cache_dir())
+            .with_progress(Some(ProgressDisplay::callback()));
+        let chain_id = state_client.fetch_chain_id()?;
+        let anchor = state_client.fetch_anchor()?;
+
+        let (report, account, notes) = match (address, view_key) {
+            (Some(address), None) => {
+                let pk = BlsPublicKey::from_slice(&bs58::decode(&address).into_vec()?)?;
+                let account = state_client.fetch_account(&pk)?;
+                let report = ExportReport {
+                    funding: Funding::Moonlight,
+                    key: address,
+                    chain_id,
+                    balance: account.balance,
+                    notes: None,
+                    out_file,
+                };
+                (report, Some(account), vec![])
+            }
+            (None, Some(view_key)) => {
+                let vk = ViewKey::from_slice(&bs58::decode(&view_key).into_vec()?)?;
+                let mut notes = vec![];
+                let mut balance = 0;
+                for (note, block_height) in state_client.fetch_notes(&vk)? {
+                    balance += note.value(Some(&vk)).unwrap_or_default();
+                    let opening = state_client.fetch_opening(&note)?;
+                    notes.push((note, block_height, opening));
+                }
+                let report = ExportReport {
+                    funding: Funding::Phoenix,
+                    key: view_key,
+                    chain_id,
+                    balance,
+                    notes: Some(notes.len()),
+                    out_file,
+                };
+                (report, None, notes)
+            }
+            _ => {
+                return Err(Error::InvalidArgument(
+                    "either --address or --view-key is required".into(),
+                ))
+            }
+        };
+
+        OfflineState {
+            chain_id,
+            account,
+            notes,
+            anchor,
+        }
+        .save(&report.out_file)?;
+
+        Ok(report)
+    }
+
+    /// Builds and signs a transaction from an exported state, without
+    /// network access
+    pub fn sign(
+        ctx: &Context,
+        state: &Path,
+        out_file: PathBuf,
+        tx: TxCommand,
+    ) -> Result<SignReport, Error> {
+        let wallet = ctx.offline_wallet(OfflineState::load(state)?)?;
+
+        let gas_limit = ctx.fixed_gas_limit("offline")?;
+        let (outcome, contract_id) = ctx.execute_tx(&wallet, &tx, gas_limit)?;
+
+        offline::save_tx(&out_file, &outcome.tx)?;
+
+        Ok(SignReport {
+            tx_id: outcome.tx_id,
+            funding: ctx.funding(),
+            sender: ctx.sender(&wallet)?,
+            contract_id,
+            out_file,
+        })
+    }
+
+    /// Broadcasts a transaction signed offline, proving it first when
+    /// funded from Phoenix notes
+    pub fn broadcast(ctx: &Context, signed_tx: &Path) -> Result<BroadcastReport, Error> {
+        let tx = offline::load_tx(signed_tx)?;
+
+        let tx = ctx.prover_client().broadcast(tx)?;
+
+        let report = if ctx.global.dry_run {
+            BroadcastReport {
+                tx_id: tx_id(&tx),
+                receipt: None,
+                dry_run: true,
+            }
+        } else if ctx.global.wait_for == WaitFor::None {
+            BroadcastReport {
+                tx_id: tx_id(&tx),
+                receipt: None,
+                dry_run: false,
+            }
+        } else {
+            let outcome = Executor::included_outcome(&ctx.client(), &tx)?;
+            BroadcastReport {
+                tx_id: outcome.tx_id,
+                receipt: outcome.receipt,
+                dry_run: false,
+            }
+        };
+
+        Ok(report)
+    }
+
+    /// Estimates the gas spent by a deployment or a call by having the node
+    /// simulate it, and recommends a gas limit
+    pub fn estimate(ctx: &Context, tx: TxCommand) -> Result<EstimateReport, Error> {
+        let wallet = ctx.wallet()?;
+
+        let (outcome, contract_id) = wallet
+            .prover()
+            .simulating(|| ctx.execute_tx(&wallet, &tx, ctx.simulation_gas_limit()))?;
+        let receipt = outcome.receipt.unwrap_or_default();
+
+        Ok(EstimateReport {
+            gas_spent: receipt.gas_spent,
+            gas_limit: ctx.recommended_gas_limit(receipt.gas_spent),
+            gas_margin: ctx.global.gas_margin,
+            funding: ctx.funding(),
+            contract_id,
+            error: receipt.error,
+        })
+    }
+
+    /// Shows the public keys of the first profiles, the Phoenix key and
+    /// Moonlight account derived at every index
+    pub fn addresses(ctx: &Context, count: u8) -> Result<AddressesReport, Error> {
+        let store = ctx.store()?;
+
+        let mut profiles = vec![];
+        if store.get_seed().is_err() {
+            // a single Moonlight secret key derives no other keys
+            let pk = BlsPublicKey::from(&store.fetch_account_secret_key(0)?);
+            profiles.push(Profile {
+                index: 0,
+                phoenix: None,
+                view_key: None,
+                moonlight: bs58::encode(pk.to_bytes()).into_string(),
+            });
+        } else {
+            for index in 0..count.max(1) as u64 {
+                let sk = store.fetch_secret_key(index)?;
+                let pk = BlsPublicKey::from(&store.fetch_account_secret_key(index)?);
+                profiles.push(Profile {
+                    index,
+                    phoenix: Some(bs58::encode(PublicKey::from(&sk).to_bytes()).into_string()),
+                    view_key: Some(bs58::encode(ViewKey::from(&sk).to_bytes()).into_string()),
+                    moonlight: bs58::encode(pk.to_bytes()).into_string(),
+                });
+            }
+        }
+
+        Ok(AddressesReport {
+            funding: ctx.funding(),
+            profile_index: ctx.profile_index(),
+            profiles,
+        })
+    }
+
+    /// Computes contract ids of a bytecode for each of the given nonces, or of
+    /// all contracts of a manifest, without network access
+    pub fn contract_id(
+        contract_path: Option<PathBuf>,
+        expected_hash: Option<String>,
+        owner: String,
+        nonces: Vec<u64>,
+        manifest_path: Option<PathBuf>,
+    ) -> Result<ContractIdReport, Error> {
+        let mut contracts = vec![];
+        match (manifest_path, contract_path) {
+            (Some(manifest_path), _) => {
+                let manifest = Manifest::load(&manifest_path)?;
+                let mut refs = References::default();
+                for entry in manifest.ordered()? {
+                    let bytecode = std::fs::read(manifest.contract_path(entry))?;
+                    let owner = entry.owner(&refs)?;
+                    let contract_id = gen_contract_id(&bytecode, entry.nonce, &owner);
+                    refs.insert(&entry.name, contract_id, owner.clone());
+                    contracts.push(ContractIdEntry {
+                        name: Some(entry.name.clone()),
+                        owner: hex::encode(owner),
+                        nonce: entry.nonce,
+                        bytecode_hash: hex::encode(bytecode_hash(&bytecode)),
+                        contract_id: hex::encode(contract_id),
+                    });
+                }
+            }
+            (None, Some(contract_path)) => {
+                let bytecode = std::fs::read(contract_path)?;
+                let hash = hex::encode(bytecode_hash(&bytecode));
+                if let Some(expected_hash) = expected_hash {
+                    if !expected_hash.eq_ignore_ascii_case(&hash) {
+                        return Err(Error::InvalidArgument(
+                            format!("bytecode hash is {}, not {}", hash, expected_hash).into(),
+                        ));
+                    }
+                }
+                let owner = hex::decode(owner)?;
+                let generator = ContractIdGenerator::new(&bytecode, &owner);
+                for nonce in nonces {
+                    contracts.push(ContractIdEntry {
+                        name: None,
+                        owner: hex::encode(&owner),
+                        nonce,
+                        bytecode_hash: hash.clone(),
+                        contract_id: hex::encode(generator.gen(nonce)),
+                    });
+                }
+            }
+            (None, None) => {
+                return Err(Error::InvalidArgument(
+                    "either --contract-path or --manifest is required".into(),
+                ))
+            }
+        }
+        Ok(ContractIdReport { contracts })
+    }
+
+    /// Searches nonces giving a contract id matching the pattern, on all
+    /// CPU cores unless told otherwise
+    #[allow(clippy::too_many_arguments)]
+    pub fn vanity(
+        ctx: &Context,
+        contract_path: &Path,
+        owner: String,
+        pattern: Pattern,
+        start_nonce: u64,
+        count: usize,
+        max_iterations: Option<u64>,
+        threads: Option<usize>,
+        deploy: Option<FnArgs>,
+    ) -> Result<VanityReport, Error> {
+        let bytecode = std::fs::read(contract_path)?;
+        let generator = ContractIdGenerator::new(&bytecode, hex::decode(&owner)?);
+        let threads = threads.unwrap_or_else(|| {
+            std::thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(1)
+        });
+        info!(
+            "Searching on {} threads, one match expected every {} nonces",
+            threads,
+            pattern.difficulty()
+        );
+
+        let search = Vanity::search(
+            &generator,
+            &pattern,
+            start_nonce,
+            count.max(1),
+            max_iterations,
+            threads,
+        );
+
+        let deployment = match (deploy, search.matches.first()) {
+            (Some(args), Some(&(nonce, _))) => Some(Commands::deploy(
+                ctx,
+                contract_path,
+                owner,
+                nonce,
+                args,
+                None,
+                false,
+            )?),
+            _ => None,
+        };
+
+        Ok(VanityReport {
+            matches: search
+                .matches
+                .into_iter()
+                .map(|(nonce, id)| VanityMatch {
+                    nonce,
+                    contract_id: hex::encode(id),
+                })
+                .collect(),
+            iterations: search.iterations,
+            deployment,
+        })
+    }
+
+    /// Creates a keystore with a new recovery phrase, reported once
+    pub fn keystore_create(
+        ctx: &Context,
+        path: &Path,
+        birthday: Option<u64>,
+    ) -> Result<KeystoreReport, Error> {
+        // no notes are owned by a new phrase before the current height
+        let birthday = birthday.or_else(|| {
+            BlockchainInquirer::block_height(&ctx.client())
+                .wait()
+                .map_err(|e| info!("Birthday height not recorded: {}", e))
+                .ok()
+        });
+        let phrase = Mnemonic::new(MnemonicType::Words24, Language::English)
+            .phrase()
+            .to_string();
+        Keystore::encrypt(SecretKind::Phrase, &phrase, &keystore::password(true)?)?
+            .with_birthday(birthday)
+            .save(path)?;
+        Ok(KeystoreReport {
+            path: path.to_path_buf(),
+            kind: SecretKind::Phrase,
+            secret: Some(phrase),
+            birthday,
+        })
+    }
+
+    /// Creates a keystore from the secret given on the command line or in
+    /// the environment, or prompted for
+    pub fn keystore_import(
+        ctx: &Context,
+        path: &Path,
+        birthday: Option<u64>,
+    ) -> Result<KeystoreReport, Error> {
+        let (kind, secret) = match (ctx.global.seed.as_str(), ctx.global.moonlight.as_str()) {
+            ("", "") => (
+                SecretKind::Phrase,
+                rpassword::prompt_password("Recovery phrase: ")?,
+            ),
+            (_, "") => (SecretKind::Phrase, ctx.global.seed.clone()),
+            ("", _) => (SecretKind::Moonlight, ctx.global.moonlight.clone()),
+            _ => {
+                return Err(Error::InvalidArgument(
+                    "either --seed or --moonlight is imported, not both".into(),
+                ))
+            }
+        };
+        Keystore::encrypt(kind, secret.trim(), &keystore::password(true)?)?
+            .with_birthday(birthday)
+            .save(path)?;
+        Ok(KeystoreReport {
+            path: path.to_path_buf(),
+            kind,
+            secret: None,
+            birthday,
+        })
+    }
+
+    /// Decrypts the secret of a keystore
+    pub fn keystore_export(path: &Path) -> Result<KeystoreReport, Error> {
+        let keystore = Keystore::load(path)?;
+        let secret = keystore.decrypt(&keystore::password(false)?)?;
+        Ok(KeystoreReport {
+            path: path.to_path_buf(),
+            kind: keystore.kind,
+            secret: Some(secret),
+            birthday: keystore.birthday,
+        })
+    }
+}
+
+/// Returns the block height scanning for notes starts from: the given
+/// height, or the relative height below the current one when it is known.
+/// The birthday is the default when neither height is given.
+fn scan_start_height(
+    block_height: u64,
+    relative_height: u64,
+    current_height: impl FnOnce() -> Option<u64>,
+    birthday: Option<u64>,
+) -> u64 {
+    let mut start_bh = block_height;
+    if relative_height != 0 {
+        if let Some(cur_bh) = current_height() {
+            start_bh = cur_bh - min(cur_bh, relative_height);
+        }
+    }
+    if start_bh == 0 && relative_height == 0 {
+        start_bh = birthday.unwrap_or_default();
+    }
+    start_bh
+}
+
+/// Parses a hexadecimal string into a contract id
+pub fn parse_contract_id(contract_id: impl AsRef<str>) -> Result<ContractId, Error> {
+    hex::decode(contract_id.as_ref())?
+        .try_into()
+        .map_err(|_| Error::InvalidArgument("contract id must be 32 bytes long".into()))
+}
+
+/// Returns rkyv serialized arguments, either decoded from hexadecimal
+/// string or encoded from JSON according to a type description
+pub fn encode_args(args: &FnArgs) -> Result<Vec<u8>, Error> {
+    match (&args.args_json, &args.args_type) {
+        (Some(json), Some(type_desc)) => {
+            let desc: TypeDesc = type_desc.parse()?;
+            let value: serde_json::Value = serde_json::from_str(json)
+                .map_err(|e| Error::InvalidArgument(format!("invalid JSON: {}", e).into()))?;
+            ArgsEncoder::encode(&desc, &value)
+        }
+        _ => Ok(hex::decode(&args.args)?),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::note_cache::NoteCache;
+
+    fn unknown() -> Option<u64> {
+        None
+    }
+
+    #[test]
+    fn scan_start_heights() {
+        // the birthday is the default
+        assert_eq!(scan_start_height(0, 0, unknown, Some(100)), 100);
+        assert_eq!(scan_start_height(0, 0, unknown, None), 0);
+        // the current height is not needed without relative height
+        let current = || -> Option<u64> { panic!("current height queried") };
+        assert_eq!(scan_start_height(0, 0, current, None), 0);
+
+        // a given height overrides the birthday, even below it
+        assert_eq!(scan_start_height(500, 0, unknown, Some(100)), 500);
+        assert_eq!(scan_start_height(50, 0, unknown, Some(100)), 50);
+
+        // a relative height overrides both
+        assert_eq!(scan_start_height(0, 50, || Some(1000), Some(100)), 950);
+        assert_eq!(scan_start_height(500, 50, || Some(1000), Some(100)), 950);
+        assert_eq!(scan_start_height(0, 2000, || Some(1000), Some(100)), 0);
+        // unless the current height is unknown, the given height being used
+        assert_eq!(scan_start_height(500, 50, unknown, Some(100)), 500);
+        assert_eq!(scan_start_height(0, 50, unknown, Some(100)), 0);
+    }
+
+    #[test]
+    fn scans_resume_from_cached_heights() {
+        let cache = NoteCache {
+            first_height: 100,
+            last_height: 200,
+            ..NoteCache::default()
+        };
+        // leaves already scanned from the birthday are not scanned again
+        let start = scan_start_height(0, 0, unknown, Some(100));
+        assert_eq!(cache.resume_height(start), 200);
+        let start = scan_start_height(0, 50, || Some(1000), Some(100));
+        assert_eq!(cache.resume_height(start), 950);
+        // a height below the heights scanned restarts scanning from it
+        let start = scan_start_height(50, 0, unknown, Some(100));
+        assert_eq!(cache.resume_height(start), 50);
+        let start = scan_start_height(0, 990, || Some(1000), Some(100));
+        assert_eq!(cache.resume_height(start), 10);
+    }
+}
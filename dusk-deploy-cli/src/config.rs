@@ -4,13 +4,21 @@
 //
 // Copyright (c) DUSK NETWORK. All rights reserved.
 
+use crate::polling::Polling;
 use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
 use toml_base_config::BaseConfig;
 
 #[derive(Debug, Default, Deserialize, Serialize, Clone)]
 pub struct BlockchainAccessConfig {
     pub rusk_address: String,
     pub prover_address: String,
+    /// Polling of the node while waiting for transactions
+    #[serde(default)]
+    pub polling: Polling,
+    /// Directory notes found are cached in [default: `~/.dusk-deploy-cli/cache`]
+    #[serde(default)]
+    pub cache_dir: Option<PathBuf>,
 }
 
 impl BaseConfig for BlockchainAccessConfig {
@@ -1,20 +1,43 @@
+use crate::args::WaitFor;
 use crate::block::Block;
+use crate::polling::WaitPolicy;
 use crate::Error;
+use dusk_bytes::Serializable;
+use dusk_plonk::prelude::Proof;
 use execution_core::transfer::phoenix::Transaction as PhoenixTransaction;
 use execution_core::{
     transfer::{moonlight::Transaction as MoonlightTransaction, Transaction},
     BlsScalar,
 };
 use rusk_http_client::{BlockchainInquirer, RuskHttpClient, RuskRequest};
-use std::borrow::Cow;
 use std::fmt::Debug;
-use std::thread;
+use std::sync::atomic::{AtomicBool, Ordering};
 use tracing::info;
 
+/// What happens to transactions handed to the prover client
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Propagation {
+    /// Proved, preverified and propagated, waiting according to the wait
+    /// policy
+    Full,
+    /// Proved and preverified, but not propagated
+    DryRun,
+    /// Returned as built, not proved, to have its execution simulated by
+    /// the node
+    Simulate,
+    /// Returned as built, neither proved nor propagated, to be broadcast
+    /// later from an online host
+    Offline,
+}
+
 pub struct DCliProverClient {
     state: RuskHttpClient,
     prover: RuskHttpClient,
     status: fn(status: &str),
+    propagation: Propagation,
+    wait: WaitPolicy,
+    /// Set while estimating gas, overriding the propagation
+    simulating: AtomicBool,
 }
 
 impl Debug for DCliProverClient {
@@ -24,14 +47,66 @@ impl Debug for DCliProverClient {
 }
 
 impl DCliProverClient {
-    pub fn new(state: RuskHttpClient, prover: RuskHttpClient) -> Self {
+    pub fn new(
+        state: RuskHttpClient,
+        prover: RuskHttpClient,
+        propagation: Propagation,
+        wait: WaitPolicy,
+    ) -> Self {
         DCliProverClient {
             state,
             prover,
             status: |a| info!("{}", a),
+            propagation,
+            wait,
+            simulating: AtomicBool::new(false),
+        }
+    }
+
+    pub fn propagation(&self) -> Propagation {
+        if self.simulating.load(Ordering::Relaxed) {
+            Propagation::Simulate
+        } else {
+            self.propagation
         }
     }
 
+    pub fn wait_for(&self) -> WaitFor {
+        self.wait.wait_for
+    }
+
+    /// Runs `f` with transactions being simulated instead of propagated
+    pub fn simulating<T>(&self, f: impl FnOnce() -> T) -> T {
+        self.simulating.store(true, Ordering::Relaxed);
+        let _reset = ResetOnDrop(&self.simulating);
+        f()
+    }
+
+    /// Proves a Phoenix transaction built with a dummy prover
+    pub fn prove(&self, utx: &PhoenixTransaction) -> Result<PhoenixTransaction, Error> {
+        self.status("Proving tx, please wait...");
+        let utx_bytes = utx.proof().to_vec();
+        let prove_req = RuskRequest::new("prove_execute", utx_bytes);
+        let proof_bytes = self.prover.call(2, "rusk", &prove_req).wait()?;
+        self.status("Proof success!");
+        let mut tx = utx.clone();
+        tx.set_proof(proof_bytes);
+        Ok(tx)
+    }
+
+    /// Broadcasts a transaction built offline, proving it first if needed
+    pub fn broadcast(&self, tx: Transaction) -> Result<Transaction, Error> {
+        let tx = match tx {
+            // unproven transactions carry the serialized circuit in place
+            // of the proof
+            Transaction::Phoenix(utx) if utx.proof().len() != Proof::SIZE => {
+                Transaction::Phoenix(self.prove(&utx)?)
+            }
+            tx => tx,
+        };
+        self.preverify_and_propagate(tx)
+    }
+
     /// Sets the callback method to send status updates
     #[allow(dead_code)]
     pub fn set_status_callback(&mut self, status: fn(&str)) {
@@ -52,40 +127,12 @@ impl wallet::ProverClient for DCliProverClient {
         &self,
         utx: &PhoenixTransaction,
     ) -> Result<Transaction, Self::Error> {
-        self.status("Proving tx, please wait...");
-        let utx_bytes = utx.proof().to_vec();
-        let prove_req = RuskRequest::new("prove_execute", utx_bytes);
-        let proof_bytes = self.prover.call(2, "rusk", &prove_req).wait()?;
-        self.status("Proof success!");
-        let mut tx = utx.clone();
-        tx.set_proof(proof_bytes);
-        let tx = Transaction::Phoenix(tx);
-        let tx_bytes = tx.to_var_bytes();
-
-        self.status("Attempt to preverify tx...");
-        let preverify_req = RuskRequest::new("preverify", tx_bytes.clone());
-        let _ = self.state.call(2, "rusk", &preverify_req).wait()?;
-        self.status("Preverify success!");
-
-        self.status("Propagating tx...");
-        let propagate_req = RuskRequest::new("propagate_tx", tx_bytes);
-        let _ = self.state.call(2, "Chain", &propagate_req).wait()?;
-        self.status("Transaction propagated!");
-
-        let tx_id = BlsScalar::hash_to_scalar(tx.to_hash_input_bytes().as_slice());
-        let tx_id_str = hex::encode(tx_id.to_bytes());
-        info!("Transaction id = {}", tx_id_str);
-        for _ in 0..20 {
-            let r = BlockchainInquirer::retrieve_tx_err(tx_id_str.clone(), &self.state).wait();
-            if r.is_ok() {
-                return match r.unwrap() {
-                    Some(err) => Err(Error::Deploy(Cow::from(err))),
-                    None => Ok(tx),
-                };
-            }
-            thread::sleep(std::time::Duration::from_secs(3));
+        // simulations execute transactions without verifying their proof
+        if let Propagation::Offline | Propagation::Simulate = self.propagation() {
+            return Ok(Transaction::Phoenix(utx.clone()));
         }
-        Err(Error::Propagate("Transaction timed out".into()))
+        let tx = Transaction::Phoenix(self.prove(utx)?);
+        self.preverify_and_propagate(tx)
     }
 
     fn propagate_moonlight_transaction(
@@ -93,37 +140,136 @@ impl wallet::ProverClient for DCliProverClient {
         tx: &MoonlightTransaction,
     ) -> Result<Transaction, Self::Error> {
         let tx = Transaction::Moonlight(tx.clone());
+        if let Propagation::Offline | Propagation::Simulate = self.propagation() {
+            return Ok(tx);
+        }
+        self.preverify_and_propagate(tx)
+    }
+}
+
+/// Clears a flag when dropped, even when unwinding
+struct ResetOnDrop<'a>(&'a AtomicBool);
+
+impl Drop for ResetOnDrop<'_> {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::Relaxed);
+    }
+}
+
+/// Returns the hexadecimal id of a transaction
+pub fn tx_id(tx: &Transaction) -> String {
+    let tx_id = BlsScalar::hash_to_scalar(tx.to_hash_input_bytes().as_slice());
+    hex::encode(tx_id.to_bytes())
+}
+
+impl DCliProverClient {
+    fn status(&self, text: &str) {
+        (self.status)(text)
+    }
+
+    /// Preverifies a transaction and, unless in dry run mode, propagates it
+    /// and waits for it according to the wait policy
+    fn preverify_and_propagate(&self, tx: Transaction) -> Result<Transaction, Error> {
         let tx_bytes = tx.to_var_bytes();
 
         self.status("Attempt to preverify tx...");
         let preverify_req = RuskRequest::new("preverify", tx_bytes.clone());
-        let _ = self.state.call(2, "rusk", &preverify_req).wait()?;
+        let _ = self
+            .state
+            .call(2, "rusk", &preverify_req)
+            .wait()
+            .map_err(|e| Error::Preverify(e.to_string().into()))?;
         self.status("Preverify success!");
 
+        let tx_id_str = tx_id(&tx);
+        if self.propagation() == Propagation::DryRun {
+            info!("Dry run, transaction {} not propagated", tx_id_str);
+            return Ok(tx);
+        }
+
         self.status("Propagating tx...");
         let propagate_req = RuskRequest::new("propagate_tx", tx_bytes);
         let _ = self.state.call(2, "Chain", &propagate_req).wait()?;
         self.status("Transaction propagated!");
 
-        let tx_id = BlsScalar::hash_to_scalar(tx.to_hash_input_bytes().as_slice());
-        let tx_id_str = hex::encode(tx_id.to_bytes());
         info!("Transaction id = {}", tx_id_str);
-        for _ in 0..20 {
-            let r = BlockchainInquirer::retrieve_tx_err(tx_id_str.clone(), &self.state).wait();
-            if r.is_ok() {
-                return match r.unwrap() {
-                    Some(err) => Err(Error::Deploy(Cow::from(err))),
-                    None => Ok(tx),
-                };
+        self.wait(&tx_id_str)?;
+        Ok(tx)
+    }
+
+    /// Waits for a propagated transaction according to the wait policy
+    fn wait(&self, tx_id: &str) -> Result<(), Error> {
+        let polling = self.wait.polling;
+        let included = |what| {
+            polling.poll(what, || {
+                // execution errors are not reported here, the transaction
+                // is considered as soon as it is included so that callers
+                // can inspect its outcome
+                match BlockchainInquirer::retrieve_spent_tx(tx_id, &self.state).wait() {
+                    Ok(spent_tx) => Ok(Some(spent_tx)),
+                    Err(rusk_http_client::Error::NotFound(_)) => Ok(None),
+                    Err(e) => Err(e.into()),
+                }
+            })
+        };
+
+        match self.wait.wait_for {
+            WaitFor::None => {}
+            WaitFor::Included => {
+                included("inclusion")?;
+            }
+            WaitFor::Confirmations(confirmations) => {
+                let spent_tx = included("inclusion")?;
+                let target = spent_tx.block_height + confirmations;
+                self.status(&format!("Waiting for {} confirmations...", confirmations));
+                polling.poll("confirmations", || {
+                    let height = BlockchainInquirer::block_height(&self.state).wait()?;
+                    Ok((height >= target).then_some(()))
+                })?;
+            }
+            WaitFor::Finalized => {
+                let spent_tx = included("inclusion")?;
+                self.status("Waiting for finality...");
+                polling.poll("finality", || {
+                    let finalized = BlockchainInquirer::is_finalized(
+                        spent_tx.block_height,
+                        &spent_tx.block_hash,
+                        &self.state,
+                    )
+                    .wait()?;
+                    Ok(finalized.then_some(()))
+                })?;
             }
-            thread::sleep(std::time::Duration::from_secs(3));
         }
-        Err(Error::Propagate("Transaction timed out".into()))
+        Ok(())
     }
 }
 
-impl DCliProverClient {
-    fn status(&self, text: &str) {
-        (self.status)(text)
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::panic::{self, AssertUnwindSafe};
+
+    #[test]
+    fn simulating_is_reset() {
+        let client = DCliProverClient::new(
+            RuskHttpClient::new("http://127.0.0.1:8080".to_string()),
+            RuskHttpClient::new("http://127.0.0.1:8080".to_string()),
+            Propagation::DryRun,
+            WaitPolicy::default(),
+        );
+
+        let result: Result<(), Error> = client.simulating(|| {
+            assert_eq!(client.propagation(), Propagation::Simulate);
+            Err(Error::Failed("simulation failed".into()))
+        });
+        assert!(result.is_err());
+        assert_eq!(client.propagation(), Propagation::DryRun);
+
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            client.simulating(|| panic!("simulation panicked"))
+        }));
+        assert!(result.is_err());
+        assert_eq!(client.propagation(), Propagation::DryRun);
     }
 }
@@ -5,6 +5,10 @@
 // Copyright (c) DUSK NETWORK. All rights reserved.
 
 use crate::block::Block;
+use crate::note_cache::NoteCache;
+use crate::offline::OfflineState;
+use crate::progress::{ProgressCallback, ProgressTracker};
+use crate::scanner::NoteScan;
 use crate::Error;
 use dusk_bytes::Serializable;
 use execution_core::transfer::phoenix::{NoteLeaf, NoteOpening};
@@ -17,11 +21,11 @@ use execution_core::{
     BlsScalar, ContractId,
 };
 use rusk_http_client::RuskHttpClient;
-use rusk_http_client::{ContractInquirer, StreamAux};
-use std::cmp::{max, Ordering};
-use std::collections::{BTreeSet, HashMap};
+use rusk_http_client::{BlockchainInquirer, ContractInquirer, StreamAux};
+use std::collections::HashMap;
 use std::fmt::Debug;
 use std::mem;
+use std::path::PathBuf;
 use std::sync::{Arc, RwLock};
 use tracing::info;
 use wallet::{EnrichedNote, StateClient};
@@ -42,54 +46,19 @@ const TRANSFER_CONTRACT_STR: &str =
 
 const ITEM_LEN: usize = mem::size_of::<NoteLeaf>();
 
-#[derive(Debug, Clone)]
-pub struct NoteBlockHeight(pub Note, pub u64);
-
-impl NoteBlockHeight {
-    pub fn as_enriched_note(&self) -> EnrichedNote {
-        (self.0.clone(), self.1)
-    }
-}
-
-impl PartialOrd<Self> for NoteBlockHeight {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.0.pos().cmp(other.0.pos()))
-    }
-}
-
-impl Ord for NoteBlockHeight {
-    fn cmp(&self, other: &Self) -> Ordering {
-        self.0.pos().cmp(other.0.pos())
-    }
-}
-
-impl PartialEq<Self> for NoteBlockHeight {
-    fn eq(&self, other: &Self) -> bool {
-        self.0.pos() == other.0.pos()
-    }
-}
-
-impl Eq for NoteBlockHeight {}
-
 pub struct DCliStateClient {
     pub client: RuskHttpClient,
-    pub cache: Arc<RwLock<HashMap<Vec<u8>, DummyCacheItem>>>,
+    /// Notes by view key, loaded from and saved to the cache directory
+    pub cache: Arc<RwLock<HashMap<Vec<u8>, NoteCache>>>,
     pub start_block_height: u64,
-}
-
-#[derive(Default, Debug, Clone)]
-pub struct DummyCacheItem {
-    notes: BTreeSet<NoteBlockHeight>,
-    last_height: u64,
-}
-
-impl DummyCacheItem {
-    fn add(&mut self, enriched_note: NoteBlockHeight) {
-        if !self.notes.contains(&enriched_note) {
-            self.last_height = enriched_note.1;
-            self.notes.insert(enriched_note);
-        }
-    }
+    /// Directory note caches are persisted in
+    pub cache_dir: Option<PathBuf>,
+    /// Exported state served instead of querying the node
+    pub offline: Option<Arc<OfflineState>>,
+    /// Callback scanning progress is reported to
+    pub progress: Option<ProgressCallback>,
+    /// View keys whose notes are scanned for together
+    pub view_keys: Vec<ViewKey>,
 }
 
 impl DCliStateClient {
@@ -99,32 +68,85 @@ impl DCliStateClient {
             client: rusk_http_client,
             cache,
             start_block_height,
+            cache_dir: None,
+            offline: None,
+            progress: None,
+            view_keys: Vec::new(),
         }
     }
-}
 
-impl Debug for DCliStateClient {
-    fn fmt(&self, _: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    /// Persists notes found in the given directory, so that later
+    /// invocations only scan new leaves
+    pub fn with_cache_dir(mut self, cache_dir: Option<PathBuf>) -> Self {
+        self.cache_dir = cache_dir;
+        self
+    }
+
+    /// Reports the progress of scans for notes to the given callback
+    pub fn with_progress(mut self, progress: Option<ProgressCallback>) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    /// Scans for the notes of the given view keys whenever notes of any
+    /// view key are fetched, in a single pass over the leaves
+    pub fn with_view_keys(mut self, view_keys: Vec<ViewKey>) -> Self {
+        self.view_keys = view_keys;
+        self
+    }
+
+    /// Creates a client serving an exported state, without network access
+    pub fn offline(state: OfflineState) -> Self {
+        Self {
+            offline: Some(Arc::new(state)),
+            ..Self::new(RuskHttpClient::new(String::new()), 0)
+        }
+    }
+
+    /// Returns the file the notes of a view key are persisted in
+    fn cache_path(&self, vk: &ViewKey) -> Result<Option<PathBuf>, Error> {
+        match &self.cache_dir {
+            Some(dir) => Ok(Some(NoteCache::path(dir, self.fetch_chain_id()?, vk))),
+            None => Ok(None),
+        }
+    }
+
+    /// Forgets the notes of a view key, in memory and on disk, so that they
+    /// are scanned for again
+    pub fn discard_cache(&self, vk: &ViewKey) -> Result<(), Error> {
+        self.cache.write().unwrap().remove(&vk.to_bytes()[..]);
+        if let Some(path) = self.cache_path(vk)? {
+            match std::fs::remove_file(path) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+                _ => {}
+            }
+        }
         Ok(())
     }
-}
 
-impl StateClient for DCliStateClient {
-    /// Error returned by the node client.
-    type Error = Error;
+    /// Returns the notes of a view key found by earlier scans
+    pub fn cached_notes(&self, vk: &ViewKey) -> Result<Vec<EnrichedNote>, Error> {
+        let cache_path = self.cache_path(vk)?;
+        Ok(self.vk_cache(vk, cache_path.as_ref()).notes)
+    }
 
-    /// Find notes for a view key, starting from the given block height.
-    fn fetch_notes(&self, vk: &ViewKey) -> Result<Vec<EnrichedNote>, Error> {
-        let cache_read = self.cache.read().unwrap();
-        let mut vk_cache = if cache_read.contains_key(&vk.to_bytes().to_vec()) {
-            cache_read.get(&vk.to_bytes().to_vec()).unwrap().clone()
-        } else {
-            DummyCacheItem::default()
-        };
+    /// Finds the notes of several view keys in a single pass over the
+    /// leaves, from the lowest height any of them resumes scanning from
+    pub fn fetch_notes_of(&self, vks: &[ViewKey]) -> Result<Vec<Vec<EnrichedNote>>, Error> {
+        let mut cache_paths = Vec::with_capacity(vks.len());
+        let mut caches = Vec::with_capacity(vks.len());
+        for vk in vks {
+            let cache_path = self.cache_path(vk)?;
+            caches.push(self.vk_cache(vk, cache_path.as_ref()));
+            cache_paths.push(cache_path);
+        }
 
-        let start_height = max(self.start_block_height, vk_cache.last_height);
+        let mut scan = NoteScan::new(vks, caches, self.start_block_height);
+        let start_height = scan.start_height();
+        // leaves of blocks following the tip height are scanned again later
+        let tip_height = BlockchainInquirer::block_height(&self.client).wait()?;
         info!("Requesting notes from height {}", start_height);
-        let mut response_notes = Vec::new();
+        let mut progress = ProgressTracker::new(self.progress.clone(), start_height, tip_height);
         let mut stream = ContractInquirer::query_contract_with_feeder(
             &self.client,
             start_height,
@@ -134,32 +156,110 @@ impl StateClient for DCliStateClient {
         .wait()?;
         StreamAux::find_items::<NoteLeaf, ITEM_LEN>(
             |leaf| {
-                if vk.owns(leaf.note.stealth_address()) {
-                    response_notes.push((leaf.note.clone(), leaf.block_height))
-                }
+                scan.push(leaf);
+                progress.leaf(leaf.block_height);
             },
             &mut stream,
         )?;
+        progress.done();
 
-        for note_block_height in response_notes {
-            // Filter out duplicated notes and update the last
-            vk_cache.add(NoteBlockHeight(note_block_height.0, note_block_height.1))
+        let mut notes = Vec::with_capacity(vks.len());
+        for ((vk, cache_path), vk_cache) in vks.iter().zip(cache_paths).zip(scan.finish(tip_height))
+        {
+            notes.push(vk_cache.notes.clone());
+            self.store_vk_cache(vk, cache_path.as_ref(), vk_cache)?;
+        }
+        Ok(notes)
+    }
+
+    /// Returns the cache of a view key, from memory or else from disk
+    fn vk_cache(&self, vk: &ViewKey, cache_path: Option<&PathBuf>) -> NoteCache {
+        let cached = self.cache.read().unwrap().get(&vk.to_bytes()[..]).cloned();
+        match (cached, cache_path) {
+            (Some(vk_cache), _) => vk_cache,
+            (None, Some(path)) => NoteCache::load(path),
+            (None, None) => NoteCache::default(),
+        }
+    }
+
+    /// Keeps the cache of a view key in memory and persists it
+    fn store_vk_cache(
+        &self,
+        vk: &ViewKey,
+        cache_path: Option<&PathBuf>,
+        vk_cache: NoteCache,
+    ) -> Result<(), Error> {
+        if let Some(path) = cache_path {
+            vk_cache.save(path)?;
         }
-        drop(cache_read);
         self.cache
             .write()
             .unwrap()
-            .insert(vk.to_bytes().to_vec(), vk_cache.clone());
+            .insert(vk.to_bytes().to_vec(), vk_cache);
+        Ok(())
+    }
+}
 
-        Ok(vk_cache
-            .notes
-            .iter()
-            .map(|nh| nh.as_enriched_note())
-            .collect())
+impl Debug for DCliStateClient {
+    fn fmt(&self, _: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Ok(())
+    }
+}
+
+impl StateClient for DCliStateClient {
+    /// Error returned by the node client.
+    type Error = Error;
+
+    /// Find notes for a view key, starting from the given block height.
+    fn fetch_notes(&self, vk: &ViewKey) -> Result<Vec<EnrichedNote>, Error> {
+        if let Some(state) = &self.offline {
+            return Ok(state
+                .notes
+                .iter()
+                .filter(|(note, _, _)| vk.owns(note.stealth_address()))
+                .map(|(note, block_height, _)| (note.clone(), *block_height))
+                .collect());
+        }
+
+        // the notes of the configured view keys are found in the same pass
+        let mut vks = self.view_keys.clone();
+        let index = match vks.iter().position(|key| key == vk) {
+            Some(index) => index,
+            None => {
+                vks.push(*vk);
+                vks.len() - 1
+            }
+        };
+        Ok(self.fetch_notes_of(&vks)?.swap_remove(index))
+    }
+
+    /// Returns the positions of the notes found spent earlier, kept with the
+    /// notes in the cache.
+    fn fetch_spent_positions(&self, vk: &ViewKey) -> Result<Vec<u64>, Self::Error> {
+        if self.offline.is_some() {
+            return Ok(vec![]);
+        }
+        let cache_path = self.cache_path(vk)?;
+        Ok(self.vk_cache(vk, cache_path.as_ref()).spent)
+    }
+
+    /// Remembers notes found spent, so that their nullifiers are not queried
+    /// again.
+    fn store_spent_positions(&self, vk: &ViewKey, positions: &[u64]) -> Result<(), Self::Error> {
+        if self.offline.is_some() {
+            return Ok(());
+        }
+        let cache_path = self.cache_path(vk)?;
+        let mut vk_cache = self.vk_cache(vk, cache_path.as_ref());
+        vk_cache.mark_spent(positions);
+        self.store_vk_cache(vk, cache_path.as_ref(), vk_cache)
     }
 
     /// Fetch the current anchor of the state.
     fn fetch_anchor(&self) -> Result<BlsScalar, Self::Error> {
+        if let Some(state) = &self.offline {
+            return Ok(state.anchor);
+        }
         let anchor = self
             .client
             .contract_query::<(), 0>(TRANSFER_CONTRACT_STR, "root", &())
@@ -174,7 +274,9 @@ impl StateClient for DCliStateClient {
         &self,
         nullifiers: &[BlsScalar],
     ) -> Result<Vec<BlsScalar>, Self::Error> {
-        if nullifiers.is_empty() {
+        // spent notes cannot be told apart offline, they are rejected when
+        // the transaction is broadcast
+        if nullifiers.is_empty() || self.offline.is_some() {
             return Ok(vec![]);
         }
         let nullifiers = nullifiers.to_vec();
@@ -190,6 +292,12 @@ impl StateClient for DCliStateClient {
 
     /// Queries the node to find the opening for a specific note.
     fn fetch_opening(&self, note: &Note) -> Result<NoteOpening, Self::Error> {
+        if let Some(state) = &self.offline {
+            return state
+                .opening(note)
+                .cloned()
+                .ok_or(Error::Offline("note opening not exported".into()));
+        }
         let data = self
             .client
             .contract_query::<_, 1024>(TRANSFER_CONTRACT_STR, "opening", note.pos())
@@ -200,6 +308,13 @@ impl StateClient for DCliStateClient {
     }
 
     fn fetch_account(&self, pk: &BlsPublicKey) -> Result<AccountData, Self::Error> {
+        if let Some(state) = &self.offline {
+            return state
+                .account
+                .as_ref()
+                .cloned()
+                .ok_or(Error::Offline("account not exported".into()));
+        }
         let data = self
             .client
             .contract_query::<_, 1024>(TRANSFER_CONTRACT_STR, "account", pk)
@@ -210,6 +325,9 @@ impl StateClient for DCliStateClient {
     }
 
     fn fetch_chain_id(&self) -> Result<u8, Error> {
+        if let Some(state) = &self.offline {
+            return Ok(state.chain_id);
+        }
         let data = self
             .client
             .contract_query::<_, { u8::SIZE }>(TRANSFER_CONTRACT_STR, "chain_id", &())
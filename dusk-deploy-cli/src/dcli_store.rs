@@ -5,16 +5,45 @@
 // Copyright (c) DUSK NETWORK. All rights reserved.
 
 use crate::Error;
-use wallet::Store;
+use bip39::{Language, Mnemonic, Seed};
+use dusk_bytes::Serializable;
+use execution_core::signatures::bls::SecretKey as BlsSecretKey;
+use execution_core::transfer::phoenix::SecretKey;
+use wallet::{derive_bls_sk, derive_phoenix_sk, Store};
 
+/// Store of a seed, or of a single Moonlight secret key. Keys are derived
+/// from the seed as by the rusk wallet, so that a recovery phrase yields the
+/// same addresses in both.
 #[derive(Debug, Clone)]
 pub struct DCliStore {
-    pub seed: [u8; 64],
+    seed: Option<[u8; 64]>,
+    account_key: Option<[u8; BlsSecretKey::SIZE]>,
 }
 
 impl DCliStore {
     pub fn new(seed: &[u8; 64]) -> Self {
-        Self { seed: *seed }
+        Self {
+            seed: Some(*seed),
+            account_key: None,
+        }
+    }
+
+    pub fn from_phrase(phrase: impl AsRef<str>) -> Result<Self, Error> {
+        Ok(Self::new(&seed_from_phrase(phrase)?))
+    }
+
+    /// Store of a single Moonlight secret key, given as a base 58 string,
+    /// deriving no other keys
+    pub fn from_moonlight_key(bs58_str: impl AsRef<str>) -> Result<Self, Error> {
+        let bytes = bs58::decode(bs58_str.as_ref()).into_vec()?;
+        let account_key: [u8; BlsSecretKey::SIZE] = bytes.try_into().map_err(|_| {
+            Error::InvalidArgument("Moonlight secret key must be 32 bytes long".into())
+        })?;
+        BlsSecretKey::from_bytes(&account_key)?;
+        Ok(Self {
+            seed: None,
+            account_key: Some(account_key),
+        })
     }
 }
 
@@ -22,6 +51,58 @@ impl Store for DCliStore {
     type Error = Error;
 
     fn get_seed(&self) -> Result<[u8; 64], Self::Error> {
-        Ok(self.seed)
+        self.seed.ok_or(Error::InvalidArgument(
+            "a Moonlight secret key derives no Phoenix keys, a seed is needed".into(),
+        ))
+    }
+
+    fn fetch_secret_key(&self, index: u64) -> Result<SecretKey, Self::Error> {
+        Ok(derive_phoenix_sk(&self.get_seed()?, key_index(index)?))
+    }
+
+    fn fetch_account_secret_key(&self, index: u64) -> Result<BlsSecretKey, Self::Error> {
+        match &self.account_key {
+            Some(account_key) => Ok(BlsSecretKey::from_bytes(account_key)?),
+            None => Ok(derive_bls_sk(&self.get_seed()?, key_index(index)?)),
+        }
+    }
+}
+
+/// Key indices are single bytes in the rusk wallet
+fn key_index(index: u64) -> Result<u8, Error> {
+    u8::try_from(index)
+        .map_err(|_| Error::InvalidArgument(format!("key index {} above 255", index).into()))
+}
+
+// converts seed phrase into a binary seed
+pub fn seed_from_phrase(phrase: impl AsRef<str>) -> Result<[u8; 64], Error> {
+    let mnemonic = Mnemonic::from_phrase(phrase.as_ref(), Language::English)
+        .map_err(|_| Error::InvalidMnemonicPhrase)?;
+    let seed_obj = Seed::new(&mnemonic, "");
+    let mut seed = [0u8; 64];
+    seed.copy_from_slice(seed_obj.as_bytes());
+    Ok(seed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seeds_from_phrases() {
+        let phrase = "abandon abandon abandon abandon abandon abandon \
+                      abandon abandon abandon abandon abandon about";
+        assert_eq!(
+            hex::encode(seed_from_phrase(phrase).unwrap()),
+            "5eb00bbddcf069084889a8ab9155568165f5c453ccb85e70811aaed6f6da5fc1\
+             9a5ac40b389cd370d086206dec8aa6c43daea6690f20ad3d8d48b2d2ce9e38e4"
+        );
+        assert!(seed_from_phrase("abandon about").is_err());
+    }
+
+    #[test]
+    fn key_indices() {
+        assert_eq!(key_index(255).unwrap(), 255);
+        assert!(key_index(256).is_err());
     }
 }
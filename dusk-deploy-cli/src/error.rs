@@ -19,6 +19,9 @@ pub enum Error {
     /// Propagate
     #[error("{0}")]
     Propagate(Cow<'static, str>),
+    /// Transaction rejected by preverification
+    #[error("Preverification failed: {0}")]
+    Preverify(Cow<'static, str>),
     /// IO
     #[error(transparent)]
     IO(Arc<std::io::Error>),
@@ -46,6 +49,37 @@ pub enum Error {
     /// Base 58 errors
     #[error(transparent)]
     Base58(Arc<bs58::decode::Error>),
+    /// Hex decoding errors
+    #[error(transparent)]
+    Hex(Arc<hex::FromHexError>),
+    /// Invalid command line argument
+    #[error("Invalid argument: {0}")]
+    InvalidArgument(Cow<'static, str>),
+    /// Type description cannot be parsed
+    #[error("Invalid type description: {0}")]
+    InvalidTypeDescription(Cow<'static, str>),
+    /// Value does not match its type description
+    #[error("Encoding error: {0}")]
+    Encoding(Cow<'static, str>),
+    /// Bytes are not a valid archive of the described type
+    #[error("Decoding error: {0}")]
+    Decoding(Cow<'static, str>),
+    /// Deployment manifest cannot be read
+    #[error("Invalid manifest: {0}")]
+    Manifest(Cow<'static, str>),
+    /// Simulated transaction failed, no gas limit can be recommended
+    #[error("Gas estimation failed: {0}")]
+    Estimate(Cow<'static, str>),
+    /// Exported state or signed transaction file is damaged, or the state
+    /// lacks data needed to build a transaction offline
+    #[error("Offline state error: {0}")]
+    Offline(Cow<'static, str>),
+    /// Keystore cannot be read, written or decrypted
+    #[error("Keystore error: {0}")]
+    Keystore(Cow<'static, str>),
+    /// Command ran but did not succeed, e.g. contract returned an error
+    #[error("{0}")]
+    Failed(Cow<'static, str>),
 }
 
 impl From<wallet::Error<DCliStore, DCliStateClient, DCliProverClient>> for Error {
@@ -83,3 +117,9 @@ impl From<bs58::decode::Error> for Error {
         Error::Base58(Arc::from(err))
     }
 }
+
+impl From<hex::FromHexError> for Error {
+    fn from(err: hex::FromHexError) -> Self {
+        Error::Hex(Arc::from(err))
+    }
+}
@@ -4,29 +4,44 @@
 //
 // Copyright (c) DUSK NETWORK. All rights reserved.
 
-use crate::dcli_prover_client::DCliProverClient;
+use crate::args::WaitFor;
+use crate::block::Block;
+use crate::dcli_prover_client::{tx_id, DCliProverClient, Propagation};
 use crate::dcli_state_client::DCliStateClient;
 use crate::dcli_store::DCliStore;
+use crate::output::Receipt;
 use execution_core::transfer::data::{
     ContractBytecode, ContractCall, ContractDeploy, TransactionData,
 };
+use execution_core::transfer::Transaction;
 use rand::prelude::*;
 use rand::rngs::StdRng;
-use rusk_http_client::ContractId;
+use rusk_http_client::{BlockchainInquirer, ContractId, RuskHttpClient};
+use serde_json::Value;
+use tracing::info;
 use wallet::Wallet;
 
 use crate::Error;
 
-fn bytecode_hash(bytecode: impl AsRef<[u8]>) -> [u8; 32] {
+pub fn bytecode_hash(bytecode: impl AsRef<[u8]>) -> [u8; 32] {
     let hash = blake3::hash(bytecode.as_ref());
     hash.into()
 }
 
+/// Outcome of a transaction included in a block, or only preverified in
+/// dry run mode, or only built and signed offline
+#[derive(Debug, Clone)]
+pub struct TxOutcome {
+    pub tx: Transaction,
+    pub tx_id: String,
+    /// Absent unless the transaction was waited for or simulated
+    pub receipt: Option<Receipt>,
+}
+
 pub struct Executor;
 
 impl Executor {
     #[allow(clippy::too_many_arguments)]
-    #[allow(dead_code)]
     pub fn deploy_via_phoenix(
         wallet: &Wallet<DCliStore, DCliStateClient, DCliProverClient>,
         bytecode: &Vec<u8>,
@@ -36,10 +51,10 @@ impl Executor {
         wallet_index: u64,
         gas_limit: u64,
         gas_price: u64,
-    ) -> Result<(), Error> {
+    ) -> Result<TxOutcome, Error> {
         let mut rng = StdRng::seed_from_u64(0xcafe);
         let hash = bytecode_hash(bytecode.as_slice());
-        wallet.phoenix_execute(
+        let tx = wallet.phoenix_execute(
             &mut rng,
             TransactionData::Deploy(ContractDeploy {
                 bytecode: ContractBytecode {
@@ -56,11 +71,10 @@ impl Executor {
             0u64,
         )?;
 
-        Ok(())
+        Self::outcome(wallet, &tx)
     }
 
     #[allow(clippy::too_many_arguments)]
-    #[allow(dead_code)]
     pub fn deploy_via_moonlight(
         wallet: &Wallet<DCliStore, DCliStateClient, DCliProverClient>,
         bytecode: &Vec<u8>,
@@ -70,9 +84,9 @@ impl Executor {
         wallet_index: u64,
         gas_limit: u64,
         gas_price: u64,
-    ) -> Result<(), Error> {
+    ) -> Result<TxOutcome, Error> {
         let hash = bytecode_hash(bytecode.as_slice());
-        wallet.moonlight_execute(
+        let tx = wallet.moonlight_execute(
             TransactionData::Deploy(ContractDeploy {
                 bytecode: ContractBytecode {
                     hash,
@@ -87,10 +101,9 @@ impl Executor {
             gas_price,
         )?;
 
-        Ok(())
+        Self::outcome(wallet, &tx)
     }
 
-    #[allow(dead_code)]
     pub fn call_via_phoenix(
         wallet: &Wallet<DCliStore, DCliStateClient, DCliProverClient>,
         contract_id: &ContractId,
@@ -99,9 +112,9 @@ impl Executor {
         wallet_index: u64,
         gas_limit: u64,
         gas_price: u64,
-    ) -> Result<(), Error> {
+    ) -> Result<TxOutcome, Error> {
         let mut rng = StdRng::seed_from_u64(0xcafe);
-        wallet.phoenix_execute(
+        let tx = wallet.phoenix_execute(
             &mut rng,
             TransactionData::Call(ContractCall {
                 contract: (*contract_id).into(),
@@ -114,10 +127,9 @@ impl Executor {
             0u64,
         )?;
 
-        Ok(())
+        Self::outcome(wallet, &tx)
     }
 
-    #[allow(dead_code)]
     pub fn call_via_moonlight(
         wallet: &Wallet<DCliStore, DCliStateClient, DCliProverClient>,
         contract_id: &ContractId,
@@ -126,8 +138,8 @@ impl Executor {
         wallet_index: u64,
         gas_limit: u64,
         gas_price: u64,
-    ) -> Result<(), Error> {
-        wallet.moonlight_execute(
+    ) -> Result<TxOutcome, Error> {
+        let tx = wallet.moonlight_execute(
             TransactionData::Call(ContractCall {
                 contract: (*contract_id).into(),
                 fn_name: method.as_ref().to_string().clone(),
@@ -138,6 +150,78 @@ impl Executor {
             gas_price,
         )?;
 
-        Ok(())
+        Self::outcome(wallet, &tx)
+    }
+
+    /// Retrieves the outcome of a transaction, if it was propagated
+    pub fn outcome(
+        wallet: &Wallet<DCliStore, DCliStateClient, DCliProverClient>,
+        tx: &Transaction,
+    ) -> Result<TxOutcome, Error> {
+        match wallet.prover().propagation() {
+            Propagation::Full if wallet.prover().wait_for() != WaitFor::None => {
+                Self::included_outcome(&wallet.state().client, tx)
+            }
+            Propagation::Simulate => Self::simulated_outcome(&wallet.state().client, tx),
+            _ => Ok(TxOutcome {
+                tx: tx.clone(),
+                tx_id: tx_id(tx),
+                receipt: None,
+            }),
+        }
+    }
+
+    /// Simulates the execution of a transaction by the node
+    fn simulated_outcome(client: &RuskHttpClient, tx: &Transaction) -> Result<TxOutcome, Error> {
+        let spent_tx = BlockchainInquirer::simulate_tx(tx.to_var_bytes(), client).wait()?;
+        Ok(TxOutcome {
+            tx: tx.clone(),
+            tx_id: tx_id(tx),
+            receipt: Some(Receipt {
+                gas_spent: spent_tx.gas_spent as u64,
+                error: spent_tx.err,
+                ..Receipt::default()
+            }),
+        })
+    }
+
+    /// Retrieves the outcome of a transaction included in a block
+    pub fn included_outcome(client: &RuskHttpClient, tx: &Transaction) -> Result<TxOutcome, Error> {
+        let tx_id = tx_id(tx);
+        Ok(TxOutcome {
+            tx: tx.clone(),
+            receipt: Some(Self::receipt(client, &tx_id)?),
+            tx_id,
+        })
+    }
+
+    /// Retrieves the receipt of a transaction included in a block, with the
+    /// contract events it emitted
+    pub fn receipt(client: &RuskHttpClient, tx_id: &str) -> Result<Receipt, Error> {
+        let spent_tx = BlockchainInquirer::retrieve_spent_tx(tx_id, client).wait()?;
+        let gas_spent = spent_tx.gas_spent as u64;
+        let gas_price = spent_tx.tx.map(|tx| tx.gas_price).unwrap_or_default();
+
+        // events are only available from archive nodes
+        let events = match BlockchainInquirer::block_events(spent_tx.block_height, client).wait() {
+            Ok(events) => events
+                .into_iter()
+                .filter(|event| event.get("origin").and_then(Value::as_str) == Some(tx_id))
+                .collect(),
+            Err(err) => {
+                info!("Contract events not available: {}", err);
+                vec![]
+            }
+        };
+
+        Ok(Receipt {
+            block_height: spent_tx.block_height,
+            block_hash: spent_tx.block_hash,
+            gas_spent,
+            gas_price,
+            fee: gas_spent.saturating_mul(gas_price),
+            error: spent_tx.err,
+            events,
+        })
     }
 }
@@ -4,7 +4,7 @@
 //
 // Copyright (c) DUSK NETWORK. All rights reserved.
 
-use blake2b_simd::Params;
+use blake2b_simd::{Params, State};
 use execution_core::CONTRACT_ID_BYTES;
 
 /// Generate a [`ContractId`] address from:
@@ -22,3 +22,34 @@ pub fn gen_contract_id(bytes: impl AsRef<[u8]>, nonce: u64, owner: impl AsRef<[u
         .try_into()
         .expect("the hash result is exactly `CONTRACT_ID_BYTES` long")
 }
+
+/// Generates contract ids of the same bytecode and owner for many nonces,
+/// hashing the bytecode only once
+#[derive(Clone)]
+pub struct ContractIdGenerator {
+    state: State,
+    owner: Vec<u8>,
+}
+
+impl ContractIdGenerator {
+    pub fn new(bytes: impl AsRef<[u8]>, owner: impl AsRef<[u8]>) -> Self {
+        let mut state = Params::new().hash_length(CONTRACT_ID_BYTES).to_state();
+        state.update(bytes.as_ref());
+        Self {
+            state,
+            owner: owner.as_ref().to_vec(),
+        }
+    }
+
+    /// Same as [`gen_contract_id`] for the given nonce
+    pub fn gen(&self, nonce: u64) -> [u8; 32] {
+        let mut hasher = self.state.clone();
+        hasher.update(&nonce.to_le_bytes()[..]);
+        hasher.update(&self.owner);
+        hasher
+            .finalize()
+            .as_bytes()
+            .try_into()
+            .expect("the hash result is exactly `CONTRACT_ID_BYTES` long")
+    }
+}
@@ -0,0 +1,311 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use crate::dcli_store::DCliStore;
+use crate::wallet_file::WalletFile;
+use crate::Error;
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Nonce};
+use argon2::{Algorithm, Argon2, Params, Version};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+#[cfg(unix)]
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::Path;
+
+/// Environment variable the keystore password is read from, before
+/// prompting for it
+pub const PASSWORD_ENV: &str = "DUSK_KEYSTORE_PASSWORD";
+
+const VERSION: u8 = 1;
+const SALT_SIZE: usize = 16;
+const NONCE_SIZE: usize = 12;
+
+/// Secret kept in a keystore
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SecretKind {
+    /// Recovery phrase, Phoenix notes funding transactions
+    Phrase,
+    /// Base 58 Moonlight secret key, a Moonlight account funding
+    /// transactions
+    Moonlight,
+}
+
+/// Argon2id parameters deriving the encryption key from the password
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Kdf {
+    /// Hexadecimal salt
+    pub salt: String,
+    /// Memory size, in KiB
+    pub m_cost: u32,
+    pub t_cost: u32,
+    pub p_cost: u32,
+}
+
+/// Secret encrypted with AES-256-GCM under a key derived from a password,
+/// stored as JSON
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Keystore {
+    pub version: u8,
+    pub kind: SecretKind,
+    pub kdf: Kdf,
+    /// Hexadecimal AES-GCM nonce
+    pub nonce: String,
+    /// Hexadecimal encrypted secret, with its authentication tag
+    pub ciphertext: String,
+    /// Block height at the creation of the secret, scanning for notes
+    /// starts from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub birthday: Option<u64>,
+}
+
+impl Keystore {
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let keystore: Keystore = serde_json::from_slice(&std::fs::read(path)?)
+            .map_err(|e| Error::Keystore(e.to_string().into()))?;
+        if keystore.version != VERSION {
+            return Err(Error::Keystore(
+                format!("unsupported version {}", keystore.version).into(),
+            ));
+        }
+        Ok(keystore)
+    }
+
+    /// Writes the keystore to a new file, readable by its owner only. An
+    /// existing file is never overwritten.
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| Error::Keystore(e.to_string().into()))?;
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        options.mode(0o600);
+        let mut file = options.open(path).map_err(|e| match e.kind() {
+            io::ErrorKind::AlreadyExists => {
+                Error::Keystore(format!("{} already exists", path.display()).into())
+            }
+            _ => e.into(),
+        })?;
+        file.write_all(json.as_bytes())?;
+        Ok(())
+    }
+
+    /// Encrypts a secret, which is checked to yield keys first
+    pub fn encrypt(kind: SecretKind, secret: &str, password: &str) -> Result<Self, Error> {
+        store(kind, secret)?;
+
+        let mut salt = [0u8; SALT_SIZE];
+        let mut nonce = [0u8; NONCE_SIZE];
+        OsRng.fill_bytes(&mut salt);
+        OsRng.fill_bytes(&mut nonce);
+        let params = Params::default();
+        let kdf = Kdf {
+            salt: hex::encode(salt),
+            m_cost: params.m_cost(),
+            t_cost: params.t_cost(),
+            p_cost: params.p_cost(),
+        };
+
+        let ciphertext = cipher(&kdf, password)?
+            .encrypt(Nonce::from_slice(&nonce), secret.as_bytes())
+            .map_err(|_| Error::Keystore("encryption failed".into()))?;
+
+        Ok(Self {
+            version: VERSION,
+            kind,
+            kdf,
+            nonce: hex::encode(nonce),
+            ciphertext: hex::encode(ciphertext),
+            birthday: None,
+        })
+    }
+
+    pub fn with_birthday(mut self, birthday: Option<u64>) -> Self {
+        self.birthday = birthday;
+        self
+    }
+
+    pub fn decrypt(&self, password: &str) -> Result<String, Error> {
+        let nonce = hex::decode(&self.nonce)?;
+        if nonce.len() != NONCE_SIZE {
+            return Err(Error::Keystore("invalid nonce".into()));
+        }
+        let secret = cipher(&self.kdf, password)?
+            .decrypt(
+                Nonce::from_slice(&nonce),
+                hex::decode(&self.ciphertext)?.as_slice(),
+            )
+            .map_err(|_| Error::Keystore("wrong password or corrupted keystore".into()))?;
+        String::from_utf8(secret).map_err(|_| Error::Keystore("invalid secret".into()))
+    }
+}
+
+fn cipher(kdf: &Kdf, password: &str) -> Result<Aes256Gcm, Error> {
+    let params = Params::new(kdf.m_cost, kdf.t_cost, kdf.p_cost, Some(32))
+        .map_err(|e| Error::Keystore(e.to_string().into()))?;
+    let mut key = [0u8; 32];
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(password.as_bytes(), &hex::decode(&kdf.salt)?, &mut key)
+        .map_err(|e| Error::Keystore(e.to_string().into()))?;
+    Aes256Gcm::new_from_slice(&key).map_err(|_| Error::Keystore("invalid key".into()))
+}
+
+fn store(kind: SecretKind, secret: &str) -> Result<DCliStore, Error> {
+    match kind {
+        SecretKind::Phrase => DCliStore::from_phrase(secret),
+        SecretKind::Moonlight => DCliStore::from_moonlight_key(secret),
+    }
+}
+
+/// Keystore of this tool, or wallet file of the rusk wallet
+#[derive(Debug, Clone)]
+pub enum KeystoreFile {
+    Keystore(Keystore),
+    WalletFile(WalletFile),
+}
+
+impl KeystoreFile {
+    /// Files with a `dat` extension are read as rusk wallet files
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("dat") => Ok(KeystoreFile::WalletFile(WalletFile::load(path)?)),
+            _ => Ok(KeystoreFile::Keystore(Keystore::load(path)?)),
+        }
+    }
+
+    /// Kind of the secret, rusk wallet files holding a seed
+    pub fn kind(&self) -> SecretKind {
+        match self {
+            KeystoreFile::Keystore(keystore) => keystore.kind,
+            KeystoreFile::WalletFile(_) => SecretKind::Phrase,
+        }
+    }
+
+    /// Block height recorded at the creation of the keystore, unknown for
+    /// rusk wallet files
+    pub fn birthday(&self) -> Option<u64> {
+        match self {
+            KeystoreFile::Keystore(keystore) => keystore.birthday,
+            KeystoreFile::WalletFile(_) => None,
+        }
+    }
+
+    /// Decrypts the file into a store kept in memory
+    pub fn unlock(&self, password: &str) -> Result<DCliStore, Error> {
+        match self {
+            KeystoreFile::Keystore(keystore) => store(keystore.kind, &keystore.decrypt(password)?),
+            KeystoreFile::WalletFile(file) => Ok(DCliStore::new(&file.seed(password)?)),
+        }
+    }
+}
+
+/// Reads the keystore password from the environment, or prompts for it,
+/// twice when a new password is chosen
+pub fn password(confirm: bool) -> Result<String, Error> {
+    if let Ok(password) = std::env::var(PASSWORD_ENV) {
+        return Ok(password);
+    }
+    let password = rpassword::prompt_password("Keystore password: ")?;
+    if confirm && rpassword::prompt_password("Repeat password: ")? != password {
+        return Err(Error::Keystore("passwords do not match".into()));
+    }
+    Ok(password)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wallet::Store;
+
+    const PHRASE: &str = "abandon abandon abandon abandon abandon abandon \
+                          abandon abandon abandon abandon abandon about";
+
+    /// Keystore with a dummy ciphertext, for tests not decrypting it
+    fn keystore(kind: SecretKind) -> Keystore {
+        Keystore {
+            version: VERSION,
+            kind,
+            kdf: Kdf {
+                salt: "00".repeat(SALT_SIZE),
+                m_cost: 8,
+                t_cost: 1,
+                p_cost: 1,
+            },
+            nonce: "00".repeat(NONCE_SIZE),
+            ciphertext: "00".into(),
+            birthday: None,
+        }
+    }
+
+    #[test]
+    fn create_and_unlock() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("keystore.json");
+        let keystore = Keystore::encrypt(SecretKind::Phrase, PHRASE, "password").unwrap();
+        assert_eq!(keystore.decrypt("password").unwrap(), PHRASE);
+        keystore.with_birthday(Some(42)).save(&path).unwrap();
+
+        let file = KeystoreFile::load(&path).unwrap();
+        assert_eq!(file.kind(), SecretKind::Phrase);
+        assert_eq!(file.birthday(), Some(42));
+        assert_eq!(
+            file.unlock("password").unwrap().get_seed().unwrap(),
+            DCliStore::from_phrase(PHRASE).unwrap().get_seed().unwrap()
+        );
+        assert!(matches!(file.unlock("wrong"), Err(Error::Keystore(_))));
+    }
+
+    #[test]
+    fn invalid_secrets_are_not_encrypted() {
+        assert!(Keystore::encrypt(SecretKind::Phrase, "not a phrase", "password").is_err());
+        assert!(Keystore::encrypt(SecretKind::Moonlight, PHRASE, "password").is_err());
+    }
+
+    #[test]
+    fn existing_files_are_not_overwritten() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("keystore.json");
+        keystore(SecretKind::Phrase).save(&path).unwrap();
+        assert!(matches!(
+            keystore(SecretKind::Moonlight).save(&path),
+            Err(Error::Keystore(_))
+        ));
+        assert_eq!(Keystore::load(&path).unwrap().kind, SecretKind::Phrase);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn files_are_readable_by_their_owner_only() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("keystore.json");
+        keystore(SecretKind::Phrase).save(&path).unwrap();
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    #[test]
+    fn unsupported_versions_are_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("keystore.json");
+        let mut keystore = keystore(SecretKind::Phrase);
+        keystore.version = VERSION + 1;
+        keystore.save(&path).unwrap();
+        assert!(matches!(Keystore::load(&path), Err(Error::Keystore(_))));
+    }
+
+    #[test]
+    fn password_from_environment() {
+        std::env::set_var(PASSWORD_ENV, "from environment");
+        assert_eq!(password(false).unwrap(), "from environment");
+        assert_eq!(password(true).unwrap(), "from environment");
+    }
+}
@@ -5,7 +5,9 @@
 // Copyright (c) DUSK NETWORK. All rights reserved.
 
 mod args;
+mod args_encoder;
 mod block;
+mod commands;
 mod config;
 mod dcli_prover_client;
 mod dcli_state_client;
@@ -13,24 +15,29 @@ mod dcli_store;
 mod error;
 mod executor;
 mod gen_id;
+mod keystore;
+mod manifest;
+mod note_cache;
+mod offline;
+mod output;
+mod polling;
+mod progress;
+mod result_decoder;
+mod scanner;
+mod type_desc;
+mod vanity;
+mod verifier;
 mod wallet_builder;
+mod wallet_file;
 
-use crate::args::Args;
-use crate::block::Block;
+use crate::args::{Args, Command, KeystoreCommand};
+use crate::commands::{Commands, Context};
 use crate::config::BlockchainAccessConfig;
 use crate::error::Error;
-use bip39::{Language, Mnemonic, Seed};
+use crate::output::report;
+use crate::vanity::Pattern;
 use clap::Parser;
-use rusk_http_client::{BlockchainInquirer, RuskHttpClient};
-use std::cmp::min;
-use std::fs::File;
-use std::io::Read;
 use toml_base_config::BaseConfig;
-use tracing::info;
-
-use crate::executor::Executor;
-use crate::gen_id::gen_contract_id;
-use crate::wallet_builder::WalletBuilder;
 
 #[tokio::main]
 #[allow(non_snake_case)]
@@ -42,106 +49,142 @@ async fn main() -> Result<(), Error> {
 
     let cli = Args::parse();
 
-    let config_path = cli.config_path.as_path();
-    let seed_phrase = cli.seed;
-    let gas_limit = cli.gas_limit;
-    let gas_price = cli.gas_price;
-    let contract_path = cli.contract_path.as_path();
-    let owner = cli.owner;
-    let nonce = cli.nonce;
-    let args = cli.args;
-    let mut start_bh = cli.block_height;
-    let rel_bh = cli.relative_height;
-    let moonlight_sk_bs58 = cli.moonlight;
-    let moonlight: bool = !moonlight_sk_bs58.is_empty();
-
-    let blockchain_access_config = BlockchainAccessConfig::load_path(config_path)?;
-
-    let mut bytecode_file = File::open(contract_path)?;
-    let mut bytecode = Vec::new();
-    bytecode_file.read_to_end(&mut bytecode)?;
-
-    let mut constructor_args: Option<Vec<u8>> = None;
-    if !args.is_empty() {
-        let v = hex::decode(args).expect("decoding constructor arguments should succeed");
-        constructor_args = Some(v);
-    }
-
-    let wallet_index = 0;
-
-    let seed = if moonlight {
-        seed_from_bs58(moonlight_sk_bs58)?
-    } else {
-        seed_from_phrase(seed_phrase)?
-    };
-
-    let owner = hex::decode(owner).expect("decoding owner should succeed");
-
-    if !moonlight && rel_bh != 0 {
-        let client = RuskHttpClient::new(blockchain_access_config.rusk_address.clone());
-        if let Ok(cur_bh) = BlockchainInquirer::block_height(&client).wait() {
-            start_bh = cur_bh - min(cur_bh, rel_bh);
+    let blockchain_access_config = match cli.command {
+        // commands run offline do not need blockchain access
+        Command::Sign { .. }
+        | Command::Addresses { .. }
+        | Command::ContractId { .. }
+        | Command::Keystore { .. }
+        | Command::Vanity { deploy: false, .. } => {
+            BlockchainAccessConfig::load_path(&cli.global.config_path).unwrap_or_default()
         }
-    }
-
-    let wallet = WalletBuilder::build(
-        blockchain_access_config.rusk_address.clone(),
-        blockchain_access_config.clone().prover_address,
-        &seed,
-        start_bh,
-    )?;
+        _ => BlockchainAccessConfig::load_path(&cli.global.config_path)?,
+    };
+    let output = cli.global.output;
+    let ctx = Context::new(blockchain_access_config, cli.global)?;
 
-    let result = if moonlight {
-        Executor::deploy_via_moonlight(
-            &wallet,
-            &bytecode,
-            &owner,
-            constructor_args,
+    match cli.command {
+        Command::Deploy {
+            contract_path,
+            owner,
             nonce,
-            wallet_index,
-            gas_limit,
-            gas_price,
-        )
-    } else {
-        Executor::deploy_via_phoenix(
-            &wallet,
-            &bytecode,
-            &owner,
-            constructor_args,
+            args,
+            manifest,
+            results_path,
+            verify_query,
+            force,
+        } => match (manifest, contract_path) {
+            (Some(manifest), _) => report(
+                output,
+                Commands::deploy_manifest(&ctx, &manifest, results_path, force),
+            ),
+            (None, Some(contract_path)) => report(
+                output,
+                Commands::deploy(
+                    &ctx,
+                    &contract_path,
+                    owner,
+                    nonce,
+                    args,
+                    verify_query,
+                    force,
+                ),
+            ),
+            (None, None) => Err(Error::InvalidArgument(
+                "either --contract-path or --manifest is required".into(),
+            )),
+        },
+        Command::Call {
+            contract_id,
+            method,
+            args,
+        } => report(output, Commands::call(&ctx, contract_id, method, args)),
+        Command::Query {
+            contract_id,
+            method,
+            args,
+            format,
+            out_file,
+            result_type,
+        } => report(
+            output,
+            Commands::query(
+                &ctx,
+                contract_id,
+                method,
+                args,
+                format,
+                out_file,
+                result_type,
+            ),
+        ),
+        Command::Balance => report(output, Commands::balance(&ctx)),
+        Command::Transfer { receiver, amount } => {
+            report(output, Commands::transfer(&ctx, receiver, amount))
+        }
+        Command::TxStatus { tx_id } => report(output, Commands::tx_status(&ctx, tx_id)),
+        Command::Export {
+            address,
+            view_key,
+            out_file,
+        } => report(output, Commands::export(&ctx, address, view_key, out_file)),
+        Command::Sign {
+            state,
+            out_file,
+            tx,
+        } => report(output, Commands::sign(&ctx, &state, out_file, tx)),
+        Command::Estimate { tx } => report(output, Commands::estimate(&ctx, tx)),
+        Command::Broadcast { signed_tx } => report(output, Commands::broadcast(&ctx, &signed_tx)),
+        Command::Addresses { count } => report(output, Commands::addresses(&ctx, count)),
+        Command::Rescan { from } => report(output, Commands::rescan(&ctx, from)),
+        Command::Keystore { command } => match command {
+            KeystoreCommand::Create { path, birthday } => {
+                report(output, Commands::keystore_create(&ctx, &path, birthday))
+            }
+            KeystoreCommand::Import { path, birthday } => {
+                report(output, Commands::keystore_import(&ctx, &path, birthday))
+            }
+            KeystoreCommand::Export { path } => report(output, Commands::keystore_export(&path)),
+        },
+        Command::ContractId {
+            contract_path,
+            bytecode_hash,
+            owner,
             nonce,
-            wallet_index,
-            gas_limit,
-            gas_price,
-        )
-    };
-
-    match result {
-        Ok(_) => info!("Deployment successful"),
-        Err(ref err) => info!("{} when deploying {:?}", err, contract_path),
-    }
-
-    if result.is_ok() {
-        let deployed_id = gen_contract_id(bytecode, nonce, owner);
-        info!("Deployed contract id: {}", hex::encode(deployed_id));
+            manifest,
+        } => report(
+            output,
+            Commands::contract_id(contract_path, bytecode_hash, owner, nonce, manifest),
+        ),
+        Command::Vanity {
+            contract_path,
+            owner,
+            prefix,
+            suffix,
+            mask,
+            start_nonce,
+            count,
+            max_iterations,
+            threads,
+            deploy,
+            args,
+        } => report(
+            output,
+            Pattern::new(prefix.as_deref(), suffix.as_deref(), mask.as_deref()).and_then(
+                |pattern| {
+                    Commands::vanity(
+                        &ctx,
+                        &contract_path,
+                        owner,
+                        pattern,
+                        start_nonce,
+                        count,
+                        max_iterations,
+                        threads,
+                        deploy.then_some(args),
+                    )
+                },
+            ),
+        ),
     }
-
-    Ok(())
-}
-
-// converts seed phrase into a binary seed
-fn seed_from_phrase(phrase: impl AsRef<str>) -> Result<[u8; 64], Error> {
-    let mnemonic = Mnemonic::from_phrase(phrase.as_ref(), Language::English)
-        .map_err(|_| Error::InvalidMnemonicPhrase)?;
-    let seed_obj = Seed::new(&mnemonic, "");
-    let mut seed = [0u8; 64];
-    seed.copy_from_slice(seed_obj.as_bytes());
-    Ok(seed)
-}
-
-// converts base 58 string into a binary seed
-fn seed_from_bs58(bs58_str: impl AsRef<str>) -> Result<[u8; 64], Error> {
-    let v = bs58::decode(bs58_str.as_ref()).into_vec()?;
-    let mut seed = [0u8; 64];
-    seed[0..32].copy_from_slice(&v);
-    Ok(seed)
 }
@@ -0,0 +1,479 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use crate::args_encoder::ArgsEncoder;
+use crate::output::DeployReports;
+use crate::type_desc::TypeDesc;
+use crate::Error;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+/// List of contracts to be deployed together, read from a TOML or JSON file
+#[derive(Debug, Deserialize)]
+pub struct Manifest {
+    #[serde(rename = "contract", default)]
+    pub contracts: Vec<ContractEntry>,
+
+    /// Directory relative contract paths are resolved against
+    #[serde(skip)]
+    pub base_dir: PathBuf,
+}
+
+/// Single contract of a manifest. Owner and constructor arguments may
+/// reference the contract ids and owners of other entries as `${name.id}`
+/// and `${name.owner}`.
+#[derive(Debug, Deserialize)]
+pub struct ContractEntry {
+    /// Name under which the contract is reported in the results
+    pub name: String,
+    /// Path to contract code, relative to the manifest file
+    pub path: PathBuf,
+    /// Hexadecimal string of contract's owner, may contain references
+    #[serde(default)]
+    pub owner: String,
+    #[serde(default)]
+    pub nonce: u64,
+    /// Hexadecimal string of rkyv serialized constructor arguments
+    pub args: Option<String>,
+    /// Constructor arguments, encoded according to `args_type`
+    pub args_json: Option<Value>,
+    pub args_type: Option<String>,
+    pub gas_limit: Option<u64>,
+    pub gas_price: Option<u64>,
+    /// Query method taking no arguments, run as a smoke test once the
+    /// contract is deployed
+    pub verify_query: Option<String>,
+}
+
+impl Manifest {
+    /// Loads a manifest, JSON if the file has a `.json` extension, TOML
+    /// otherwise
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let content = std::fs::read_to_string(path)?;
+        let mut manifest: Manifest = match path.extension() {
+            Some(ext) if ext == "json" => {
+                serde_json::from_str(&content).map_err(|e| Error::Manifest(e.to_string().into()))?
+            }
+            _ => toml::from_str(&content).map_err(|e| Error::Manifest(e.to_string().into()))?,
+        };
+        manifest.base_dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+
+        let mut names = HashSet::new();
+        for entry in manifest.contracts.iter() {
+            if !names.insert(entry.name.as_str()) {
+                return Err(Error::Manifest(
+                    format!("duplicate contract name '{}'", entry.name).into(),
+                ));
+            }
+        }
+        Ok(manifest)
+    }
+
+    pub fn contract_path(&self, entry: &ContractEntry) -> PathBuf {
+        self.base_dir.join(&entry.path)
+    }
+
+    /// Returns the entries in deployment order: every entry comes after the
+    /// entries it references, the manifest order being kept otherwise
+    pub fn ordered(&self) -> Result<Vec<&ContractEntry>, Error> {
+        let index: HashMap<&str, usize> = self
+            .contracts
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| (entry.name.as_str(), i))
+            .collect();
+
+        let mut deps = Vec::with_capacity(self.contracts.len());
+        for entry in self.contracts.iter() {
+            let mut entry_deps = HashSet::new();
+            for name in entry.dependencies()? {
+                let i = index.get(name).ok_or_else(|| {
+                    Error::Manifest(
+                        format!("'{}' references unknown contract '{}'", entry.name, name).into(),
+                    )
+                })?;
+                entry_deps.insert(*i);
+            }
+            deps.push(entry_deps);
+        }
+
+        let mut done = vec![false; self.contracts.len()];
+        let mut ordered = Vec::with_capacity(self.contracts.len());
+        while ordered.len() < self.contracts.len() {
+            let next = (0..self.contracts.len())
+                .find(|&i| !done[i] && deps[i].iter().all(|&d| done[d]))
+                .ok_or_else(|| Error::Manifest("circular references between contracts".into()))?;
+            done[next] = true;
+            ordered.push(&self.contracts[next]);
+        }
+        Ok(ordered)
+    }
+}
+
+impl ContractEntry {
+    /// Returns the owner, with references substituted
+    pub fn owner(&self, refs: &References) -> Result<Vec<u8>, Error> {
+        Ok(hex::decode(refs.substitute(&self.owner)?)?)
+    }
+
+    /// Returns rkyv serialized constructor arguments, if any, with
+    /// references substituted
+    pub fn init_args(&self, refs: &References) -> Result<Option<Vec<u8>>, Error> {
+        let args = match (&self.args, &self.args_json, &self.args_type) {
+            (Some(_), Some(_), _) => {
+                return Err(Error::Manifest(
+                    format!("'{}' has both args and args_json", self.name).into(),
+                ))
+            }
+            (_, Some(value), Some(type_desc)) => {
+                let desc: TypeDesc = type_desc.parse()?;
+                ArgsEncoder::encode(&desc, &refs.substitute_json(value)?)?
+            }
+            (_, Some(_), None) => {
+                return Err(Error::Manifest(
+                    format!("'{}' has args_json without args_type", self.name).into(),
+                ))
+            }
+            (Some(args), None, _) => hex::decode(refs.substitute(args)?)?,
+            (None, None, _) => vec![],
+        };
+        Ok(if args.is_empty() { None } else { Some(args) })
+    }
+
+    /// Names of the entries referenced by this entry
+    fn dependencies(&self) -> Result<Vec<&str>, Error> {
+        let mut strings = vec![self.owner.as_str()];
+        if let Some(args) = &self.args {
+            strings.push(args);
+        }
+        if let Some(value) = &self.args_json {
+            json_strings(value, &mut strings);
+        }
+
+        let mut deps = vec![];
+        for s in strings {
+            for reference in parse_references(s)? {
+                deps.push(reference.name);
+            }
+        }
+        Ok(deps)
+    }
+}
+
+fn json_strings<'a>(value: &'a Value, strings: &mut Vec<&'a str>) {
+    match value {
+        Value::String(s) => strings.push(s),
+        Value::Array(values) => values.iter().for_each(|v| json_strings(v, strings)),
+        Value::Object(map) => map.values().for_each(|v| json_strings(v, strings)),
+        _ => {}
+    }
+}
+
+/// Reference to another manifest entry, `${name.id}` standing for the
+/// hexadecimal contract id and `${name.owner}` for the hexadecimal owner
+struct Reference<'a> {
+    /// Position of the reference in the containing string
+    start: usize,
+    end: usize,
+    name: &'a str,
+    field: RefField,
+}
+
+enum RefField {
+    Id,
+    Owner,
+}
+
+fn parse_references(s: &str) -> Result<Vec<Reference<'_>>, Error> {
+    let mut references = vec![];
+    let mut pos = 0;
+    while let Some(offset) = s[pos..].find("${") {
+        let start = pos + offset;
+        let len = s[start..]
+            .find('}')
+            .ok_or_else(|| Error::Manifest(format!("unterminated reference in '{}'", s).into()))?;
+        let end = start + len + 1;
+        let (name, field) = s[start + 2..end - 1]
+            .rsplit_once('.')
+            .ok_or_else(|| Error::Manifest(format!("invalid reference in '{}'", s).into()))?;
+        let field = match field {
+            "id" => RefField::Id,
+            "owner" => RefField::Owner,
+            _ => {
+                return Err(Error::Manifest(
+                    format!("unknown reference field '{}' in '{}'", field, s).into(),
+                ))
+            }
+        };
+        references.push(Reference {
+            start,
+            end,
+            name,
+            field,
+        });
+        pos = end;
+    }
+    Ok(references)
+}
+
+/// Contract ids and owners of already resolved entries, by name
+#[derive(Debug, Default)]
+pub struct References {
+    ids: HashMap<String, [u8; 32]>,
+    owners: HashMap<String, Vec<u8>>,
+}
+
+impl References {
+    pub fn insert(&mut self, name: impl Into<String>, id: [u8; 32], owner: Vec<u8>) {
+        let name = name.into();
+        self.ids.insert(name.clone(), id);
+        self.owners.insert(name, owner);
+    }
+
+    /// Replaces all references in a string by their hexadecimal values
+    pub fn substitute(&self, s: &str) -> Result<String, Error> {
+        let mut result = String::with_capacity(s.len());
+        let mut pos = 0;
+        for reference in parse_references(s)? {
+            result.push_str(&s[pos..reference.start]);
+            let value = match reference.field {
+                RefField::Id => self.ids.get(reference.name).map(hex::encode),
+                RefField::Owner => self.owners.get(reference.name).map(hex::encode),
+            }
+            .ok_or_else(|| {
+                Error::Manifest(format!("unresolved reference to '{}'", reference.name).into())
+            })?;
+            result.push_str(&value);
+            pos = reference.end;
+        }
+        result.push_str(&s[pos..]);
+        Ok(result)
+    }
+
+    /// Replaces all references in strings contained in a JSON value
+    pub fn substitute_json(&self, value: &Value) -> Result<Value, Error> {
+        Ok(match value {
+            Value::String(s) => Value::String(self.substitute(s)?),
+            Value::Array(values) => Value::Array(
+                values
+                    .iter()
+                    .map(|v| self.substitute_json(v))
+                    .collect::<Result<_, _>>()?,
+            ),
+            Value::Object(map) => Value::Object(
+                map.iter()
+                    .map(|(k, v)| Ok((k.clone(), self.substitute_json(v)?)))
+                    .collect::<Result<_, Error>>()?,
+            ),
+            v => v.clone(),
+        })
+    }
+}
+
+/// Writes deployment reports of manifest contracts to a JSON file
+pub fn write_results(path: &Path, reports: &DeployReports) -> Result<(), Error> {
+    let json =
+        serde_json::to_string_pretty(reports).map_err(|e| Error::Manifest(e.to_string().into()))?;
+    std::fs::write(path, json)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest(toml: &str) -> Manifest {
+        toml::from_str(toml).unwrap()
+    }
+
+    fn names(ordered: Vec<&ContractEntry>) -> Vec<&str> {
+        ordered.iter().map(|entry| entry.name.as_str()).collect()
+    }
+
+    #[test]
+    fn ordered_after_references() {
+        let manifest = manifest(
+            r#"
+            [[contract]]
+            name = "a"
+            path = "a.wasm"
+            owner = "${c.owner}"
+
+            [[contract]]
+            name = "b"
+            path = "b.wasm"
+
+            [[contract]]
+            name = "c"
+            path = "c.wasm"
+            args = "${b.id}"
+
+            [[contract]]
+            name = "d"
+            path = "d.wasm"
+            args_json = { x = ["${a.id}", "${c.id}"] }
+            args_type = "{x: [String; 2]}"
+            "#,
+        );
+        assert_eq!(names(manifest.ordered().unwrap()), ["b", "c", "a", "d"]);
+    }
+
+    #[test]
+    fn manifest_order_kept_without_references() {
+        let manifest = manifest(
+            r#"
+            [[contract]]
+            name = "z"
+            path = "z.wasm"
+
+            [[contract]]
+            name = "a"
+            path = "a.wasm"
+            "#,
+        );
+        assert_eq!(names(manifest.ordered().unwrap()), ["z", "a"]);
+    }
+
+    #[test]
+    fn circular_and_unknown_references() {
+        let circular = manifest(
+            r#"
+            [[contract]]
+            name = "a"
+            path = "a.wasm"
+            args = "${b.id}"
+
+            [[contract]]
+            name = "b"
+            path = "b.wasm"
+            owner = "${a.owner}"
+            "#,
+        );
+        assert!(circular.ordered().is_err());
+
+        let self_reference = manifest(
+            r#"
+            [[contract]]
+            name = "a"
+            path = "a.wasm"
+            args = "${a.id}"
+            "#,
+        );
+        assert!(self_reference.ordered().is_err());
+
+        let unknown = manifest(
+            r#"
+            [[contract]]
+            name = "a"
+            path = "a.wasm"
+            args = "${b.id}"
+            "#,
+        );
+        assert!(unknown.ordered().is_err());
+    }
+
+    #[test]
+    fn references_substituted() {
+        let mut refs = References::default();
+        refs.insert("a", [0xab; 32], vec![0x01, 0x02]);
+
+        assert_eq!(
+            refs.substitute("00${a.owner}ff${a.id}").unwrap(),
+            format!("000102ff{}", "ab".repeat(32))
+        );
+        assert_eq!(refs.substitute("no references").unwrap(), "no references");
+        assert_eq!(
+            refs.substitute_json(&serde_json::json!({"k": ["${a.owner}", 1]}))
+                .unwrap(),
+            serde_json::json!({"k": ["0102", 1]})
+        );
+
+        for s in ["${b.id}", "${a.name}", "${a.id", "${a}"] {
+            assert!(refs.substitute(s).is_err(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn init_args() {
+        let mut refs = References::default();
+        refs.insert("a", [0; 32], vec![0xff]);
+        let manifest = manifest(
+            r#"
+            [[contract]]
+            name = "raw"
+            path = "raw.wasm"
+            owner = "${a.owner}"
+            args = "01${a.owner}"
+
+            [[contract]]
+            name = "none"
+            path = "none.wasm"
+
+            [[contract]]
+            name = "both"
+            path = "both.wasm"
+            args = "01"
+            args_json = 1
+
+            [[contract]]
+            name = "untyped"
+            path = "untyped.wasm"
+            args_json = 1
+            "#,
+        );
+        let entries = &manifest.contracts;
+        assert_eq!(entries[0].owner(&refs).unwrap(), vec![0xff]);
+        assert_eq!(entries[0].init_args(&refs).unwrap(), Some(vec![0x01, 0xff]));
+        assert_eq!(entries[1].init_args(&refs).unwrap(), None);
+        assert!(entries[2].init_args(&refs).is_err());
+        assert!(entries[3].init_args(&refs).is_err());
+    }
+
+    #[test]
+    fn load_toml_and_json() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+
+        let toml_path = dir.join("manifest.toml");
+        std::fs::write(
+            &toml_path,
+            "[[contract]]\nname = \"a\"\npath = \"a.wasm\"\nnonce = 2\n\n\
+             [[contract]]\nname = \"b\"\npath = \"/abs/b.wasm\"\nargs = \"00\"\n",
+        )
+        .unwrap();
+        let manifest = Manifest::load(&toml_path).unwrap();
+        assert_eq!(manifest.contracts.len(), 2);
+        assert_eq!(manifest.contracts[0].nonce, 2);
+        assert_eq!(
+            manifest.contract_path(&manifest.contracts[0]),
+            dir.join("a.wasm")
+        );
+        assert_eq!(
+            manifest.contract_path(&manifest.contracts[1]),
+            PathBuf::from("/abs/b.wasm")
+        );
+
+        let json_path = dir.join("manifest.json");
+        std::fs::write(
+            &json_path,
+            r#"{"contract": [{"name": "a", "path": "a.wasm", "gas_limit": 10}]}"#,
+        )
+        .unwrap();
+        let manifest = Manifest::load(&json_path).unwrap();
+        assert_eq!(manifest.contracts[0].gas_limit, Some(10));
+
+        let duplicate_path = dir.join("duplicate.toml");
+        std::fs::write(
+            &duplicate_path,
+            "[[contract]]\nname = \"a\"\npath = \"a.wasm\"\n\n\
+             [[contract]]\nname = \"a\"\npath = \"b.wasm\"\n",
+        )
+        .unwrap();
+        assert!(Manifest::load(&duplicate_path).is_err());
+    }
+}
@@ -0,0 +1,189 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use crate::Error;
+use bytecheck::CheckBytes;
+use dusk_bytes::Serializable;
+use execution_core::transfer::phoenix::{Note, ViewKey};
+use rkyv::{AlignedVec, Archive, Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+/// Notes of a view key on a network, with the range of block heights
+/// scanned for them, persisted so that only new leaves are fetched
+#[derive(Debug, Clone, Default, Archive, Serialize, Deserialize)]
+#[archive_attr(derive(CheckBytes))]
+pub struct NoteCache {
+    /// Block height scanning started from
+    pub first_height: u64,
+    /// Block height up to which leaves were scanned
+    pub last_height: u64,
+    /// Notes with the heights of their blocks, ordered by position
+    pub notes: Vec<(Note, u64)>,
+    /// Positions of the notes found spent, sorted
+    pub spent: Vec<u64>,
+}
+
+impl NoteCache {
+    /// File caching the notes of a view key on the network of the given
+    /// chain id, named after a hash of the view key
+    pub fn path(dir: &Path, chain_id: u8, vk: &ViewKey) -> PathBuf {
+        let hash = blake3::hash(&vk.to_bytes());
+        dir.join(format!("{}-{}.notes", chain_id, &hash.to_hex()[..32]))
+    }
+
+    /// Loads a cache, a missing or unreadable file giving an empty one
+    pub fn load(path: &Path) -> Self {
+        let Ok(content) = std::fs::read(path) else {
+            return Self::default();
+        };
+        let mut bytes = AlignedVec::new();
+        bytes.extend_from_slice(&content);
+        let mut cache: Self = rkyv::from_bytes(&bytes).unwrap_or_else(|_| {
+            info!("Ignoring unreadable note cache {:?}", path);
+            Self::default()
+        });
+        // spent positions are searched by bisection
+        cache.spent.sort_unstable();
+        cache.spent.dedup();
+        cache
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let bytes = rkyv::to_bytes::<_, 4096>(self).map_err(|_| Error::Rkyv)?;
+        std::fs::write(path, bytes)?;
+        Ok(())
+    }
+
+    /// Height scanning resumes from, given the height it is asked to start
+    /// from. Notes below the heights already scanned are not cached, so
+    /// scanning then restarts from the given height.
+    pub fn resume_height(&self, start_height: u64) -> u64 {
+        if self.last_height == 0 || start_height < self.first_height {
+            start_height
+        } else {
+            self.last_height.max(start_height)
+        }
+    }
+
+    /// Records notes found by scanning from `from_height` up to
+    /// `to_height`, skipping notes already known
+    pub fn update(&mut self, from_height: u64, to_height: u64, notes: Vec<(Note, u64)>) {
+        if from_height < self.first_height || self.last_height == 0 {
+            self.first_height = from_height;
+        }
+        self.last_height = self.last_height.max(to_height);
+        for (note, block_height) in notes {
+            if let Err(i) = self
+                .notes
+                .binary_search_by_key(note.pos(), |(n, _)| *n.pos())
+            {
+                self.notes.insert(i, (note, block_height));
+            }
+        }
+    }
+
+    /// Records notes found spent, by position
+    pub fn mark_spent(&mut self, positions: &[u64]) {
+        for pos in positions {
+            if let Err(i) = self.spent.binary_search(pos) {
+                self.spent.insert(i, *pos);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use execution_core::transfer::phoenix::{PublicKey, SecretKey};
+    use execution_core::JubJubScalar;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn notes(positions: &[u64]) -> Vec<(Note, u64)> {
+        let mut rng = StdRng::seed_from_u64(0xcafe);
+        let pk = PublicKey::from(&SecretKey::random(&mut rng));
+        positions
+            .iter()
+            .map(|&pos| {
+                let blinder = [JubJubScalar::from(1u64), JubJubScalar::from(2u64)];
+                let mut note = Note::transparent(&mut rng, &pk, &pk, pos, blinder);
+                note.set_pos(pos);
+                (note, pos * 10)
+            })
+            .collect()
+    }
+
+    fn positions(cache: &NoteCache) -> Vec<u64> {
+        cache.notes.iter().map(|(note, _)| *note.pos()).collect()
+    }
+
+    #[test]
+    fn resume_height() {
+        let mut cache = NoteCache::default();
+        assert_eq!(cache.resume_height(100), 100);
+
+        cache.update(100, 200, vec![]);
+        assert_eq!(cache.resume_height(100), 200);
+        assert_eq!(cache.resume_height(150), 200);
+        assert_eq!(cache.resume_height(300), 300);
+        // notes below the first height scanned are not cached
+        assert_eq!(cache.resume_height(50), 50);
+    }
+
+    #[test]
+    fn update_keeps_notes_ordered_and_unique() {
+        let mut cache = NoteCache::default();
+        cache.update(100, 200, notes(&[5, 1, 3]));
+        assert_eq!(positions(&cache), [1, 3, 5]);
+        assert_eq!((cache.first_height, cache.last_height), (100, 200));
+
+        cache.update(200, 300, notes(&[3, 4, 9]));
+        assert_eq!(positions(&cache), [1, 3, 4, 5, 9]);
+        assert_eq!((cache.first_height, cache.last_height), (100, 300));
+
+        // rescanning from below keeps the notes and the last height
+        cache.update(0, 150, notes(&[0]));
+        assert_eq!(positions(&cache), [0, 1, 3, 4, 5, 9]);
+        assert_eq!((cache.first_height, cache.last_height), (0, 300));
+    }
+
+    #[test]
+    fn mark_spent_keeps_positions_sorted_and_unique() {
+        let mut cache = NoteCache::default();
+        cache.mark_spent(&[9, 2, 5]);
+        assert_eq!(cache.spent, [2, 5, 9]);
+        cache.mark_spent(&[5, 0, 11, 0]);
+        assert_eq!(cache.spent, [0, 2, 5, 9, 11]);
+        cache.mark_spent(&[]);
+        assert_eq!(cache.spent, [0, 2, 5, 9, 11]);
+    }
+
+    #[test]
+    fn save_and_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cache").join("cache.notes");
+        assert_eq!(NoteCache::load(&path).last_height, 0);
+
+        let mut cache = NoteCache::default();
+        cache.update(10, 20, notes(&[2, 7]));
+        cache.mark_spent(&[7]);
+        cache.save(&path).unwrap();
+
+        let loaded = NoteCache::load(&path);
+        assert_eq!((loaded.first_height, loaded.last_height), (10, 20));
+        assert_eq!(positions(&loaded), [2, 7]);
+        assert_eq!(loaded.notes[1].1, 70);
+        assert_eq!(loaded.spent, [7]);
+
+        std::fs::write(&path, b"garbage").unwrap();
+        assert_eq!(NoteCache::load(&path).last_height, 0);
+    }
+}
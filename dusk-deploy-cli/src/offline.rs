@@ -0,0 +1,148 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use crate::Error;
+use bytecheck::CheckBytes;
+use execution_core::transfer::moonlight::AccountData;
+use execution_core::transfer::phoenix::{Note, NoteOpening};
+use execution_core::transfer::Transaction;
+use execution_core::BlsScalar;
+use rkyv::{AlignedVec, Archive, Deserialize, Serialize};
+use std::path::Path;
+
+/// Size of the blake3 hash ending state and transaction files
+const CHECKSUM_SIZE: usize = 32;
+
+/// Chain state needed to build and sign transactions on a host without
+/// network access, exported by an online host
+#[derive(Debug, Clone, Archive, Serialize, Deserialize)]
+#[archive_attr(derive(CheckBytes))]
+pub struct OfflineState {
+    pub chain_id: u8,
+    /// Moonlight account of the funding key
+    pub account: Option<AccountData>,
+    /// Phoenix notes of the funding key, with their block heights and
+    /// openings
+    pub notes: Vec<(Note, u64, NoteOpening)>,
+    pub anchor: BlsScalar,
+}
+
+impl OfflineState {
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let mut bytes = AlignedVec::new();
+        bytes.extend_from_slice(&read_file(path)?);
+        rkyv::from_bytes(&bytes).map_err(|_| Error::Rkyv)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        let bytes = rkyv::to_bytes::<_, 4096>(self).map_err(|_| Error::Rkyv)?;
+        write_file(path, &bytes)
+    }
+
+    pub fn opening(&self, note: &Note) -> Option<&NoteOpening> {
+        self.notes
+            .iter()
+            .find(|(n, _, _)| n.pos() == note.pos())
+            .map(|(_, _, opening)| opening)
+    }
+}
+
+/// Reads a transaction signed offline
+pub fn load_tx(path: &Path) -> Result<Transaction, Error> {
+    Ok(Transaction::from_slice(&read_file(path)?)?)
+}
+
+pub fn save_tx(path: &Path, tx: &Transaction) -> Result<(), Error> {
+    write_file(path, &tx.to_var_bytes())
+}
+
+/// Writes the bytes followed by their hash, so that files truncated or
+/// altered while carried between hosts are detected when read
+fn write_file(path: &Path, bytes: &[u8]) -> Result<(), Error> {
+    let mut file = bytes.to_vec();
+    file.extend_from_slice(blake3::hash(bytes).as_bytes());
+    std::fs::write(path, file)?;
+    Ok(())
+}
+
+fn read_file(path: &Path) -> Result<Vec<u8>, Error> {
+    let mut bytes = std::fs::read(path)?;
+    let len = bytes.len().saturating_sub(CHECKSUM_SIZE);
+    if bytes.len() < CHECKSUM_SIZE || blake3::hash(&bytes[..len]).as_bytes() != &bytes[len..] {
+        return Err(Error::Offline(
+            format!("{} is truncated or altered", path.display()).into(),
+        ));
+    }
+    bytes.truncate(len);
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use execution_core::signatures::bls::SecretKey as BlsSecretKey;
+    use execution_core::transfer::moonlight::Transaction as MoonlightTransaction;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    /// Truncated and altered copies of a file
+    fn damaged(path: &Path) -> Vec<Vec<u8>> {
+        let bytes = std::fs::read(path).unwrap();
+        let mut altered = bytes.clone();
+        altered[bytes.len() / 2] ^= 1;
+        vec![
+            bytes[..bytes.len() - 1].to_vec(),
+            bytes[..CHECKSUM_SIZE - 1].to_vec(),
+            altered,
+        ]
+    }
+
+    #[test]
+    fn states() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.bin");
+        let state = OfflineState {
+            chain_id: 2,
+            account: Some(AccountData {
+                nonce: 3,
+                balance: 1000,
+            }),
+            notes: vec![],
+            anchor: BlsScalar::from(7u64),
+        };
+        state.save(&path).unwrap();
+
+        let loaded = OfflineState::load(&path).unwrap();
+        assert_eq!(loaded.chain_id, 2);
+        assert_eq!(
+            loaded.account.map(|a| (a.nonce, a.balance)),
+            Some((3, 1000))
+        );
+        assert_eq!(loaded.anchor, BlsScalar::from(7u64));
+
+        for bytes in damaged(&path) {
+            std::fs::write(&path, bytes).unwrap();
+            assert!(matches!(OfflineState::load(&path), Err(Error::Offline(_))));
+        }
+    }
+
+    #[test]
+    fn transactions() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("deploy.tx");
+        let sk = BlsSecretKey::random(&mut StdRng::seed_from_u64(0xdead));
+        let tx = Transaction::Moonlight(
+            MoonlightTransaction::new(&sk, None, 0, 0, 100_000, 1, 4, 2, None).unwrap(),
+        );
+        save_tx(&path, &tx).unwrap();
+        assert_eq!(load_tx(&path).unwrap().to_var_bytes(), tx.to_var_bytes());
+
+        for bytes in damaged(&path) {
+            std::fs::write(&path, bytes).unwrap();
+            assert!(matches!(load_tx(&path), Err(Error::Offline(_))));
+        }
+    }
+}
@@ -0,0 +1,670 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use crate::args::OutputFormat;
+use crate::keystore::SecretKind;
+use crate::Error;
+use clap::ValueEnum;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::PathBuf;
+use tracing::{info, warn};
+
+/// Result of a command, either logged in human readable form or printed
+/// on stdout as a JSON object
+pub trait Report: Serialize {
+    /// Logs the result in human readable form
+    fn log(&self);
+
+    /// Returns the error of a command which ran but failed
+    fn error(&self) -> Option<String> {
+        None
+    }
+}
+
+/// Outputs the result of a command in the given format, turning failures
+/// into errors so that the process exits with a non-zero code
+pub fn report<R: Report>(format: OutputFormat, result: Result<R, Error>) -> Result<(), Error> {
+    match result {
+        Ok(report) => {
+            match format {
+                OutputFormat::Text => report.log(),
+                OutputFormat::Json => print_json(&report)?,
+            }
+            match report.error() {
+                Some(err) => Err(Error::Failed(err.into())),
+                None => Ok(()),
+            }
+        }
+        Err(err) => {
+            if let OutputFormat::Json = format {
+                print_json(&serde_json::json!({ "error": err.to_string() }))?;
+            }
+            Err(err)
+        }
+    }
+}
+
+fn print_json(value: &impl Serialize) -> Result<(), Error> {
+    let json =
+        serde_json::to_string_pretty(value).map_err(|e| Error::Failed(e.to_string().into()))?;
+    println!("{}", json);
+    Ok(())
+}
+
+fn log_dry_run(tx_id: &str, tx_file: &Option<PathBuf>) {
+    info!("Dry run, transaction {} accepted by preverification", tx_id);
+    if let Some(tx_file) = tx_file {
+        info!("Transaction written to {:?}", tx_file);
+    }
+}
+
+fn log_propagated(tx_id: &str) {
+    info!(
+        "Transaction {} propagated, not waiting for inclusion",
+        tx_id
+    );
+}
+
+/// Source of funds for transactions
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Funding {
+    Phoenix,
+    Moonlight,
+}
+
+impl fmt::Display for Funding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Funding::Phoenix => write!(f, "phoenix"),
+            Funding::Moonlight => write!(f, "moonlight"),
+        }
+    }
+}
+
+/// Outcome of a transaction included in a block
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Receipt {
+    pub block_height: u64,
+    pub block_hash: String,
+    pub gas_spent: u64,
+    pub gas_price: u64,
+    /// Fee charged, gas spent times gas price
+    pub fee: u64,
+    pub error: Option<String>,
+    /// Contract events emitted by the transaction
+    pub events: Vec<Value>,
+}
+
+impl Receipt {
+    fn log(&self) {
+        info!("Block height: {}", self.block_height);
+        info!("Block hash: {}", self.block_hash);
+        info!("Gas spent: {}", self.gas_spent);
+        info!("Fee: {} (gas price {})", self.fee, self.gas_price);
+        for event in self.events.iter() {
+            info!("Event: {}", event);
+        }
+    }
+
+    fn error(receipt: &Option<Receipt>) -> Option<String> {
+        receipt.as_ref().and_then(|receipt| receipt.error.clone())
+    }
+}
+
+/// Checks of a deployed contract against its deployment transaction
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Verification {
+    /// Contract found at the computed id
+    pub contract_exists: bool,
+    /// Query method run as a smoke test
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub smoke_test: Option<String>,
+    /// First check which failed
+    pub error: Option<String>,
+}
+
+impl Verification {
+    fn log(&self) {
+        match (&self.error, &self.smoke_test) {
+            (Some(err), _) => info!("Verification failed: {}", err),
+            (None, Some(method)) => info!("Contract exists, smoke test {} succeeded", method),
+            (None, None) => info!("Contract exists"),
+        }
+    }
+
+    fn error(verification: &Option<Verification>) -> Option<String> {
+        verification
+            .as_ref()
+            .and_then(|verification| verification.error.as_ref())
+            .map(|err| format!("verification failed: {}", err))
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DeployReport {
+    pub contract_id: String,
+    pub nonce: u64,
+    /// Empty if the contract was already deployed
+    pub tx_id: String,
+    /// Absent unless waiting for the transaction
+    #[serde(flatten)]
+    pub receipt: Option<Receipt>,
+    pub funding: Funding,
+    /// Base 58 public key of the funding account or note owner
+    pub sender: String,
+    pub bytecode_hash: String,
+    /// Absent unless the transaction was included without error
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub verification: Option<Verification>,
+    /// Deployment skipped, the contract id being already deployed
+    pub already_deployed: bool,
+    /// Transaction only preverified
+    pub dry_run: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tx_file: Option<PathBuf>,
+}
+
+impl Report for DeployReport {
+    fn log(&self) {
+        if self.already_deployed {
+            warn!("Contract {} already deployed, skipped", self.contract_id);
+            return;
+        }
+        if self.dry_run {
+            log_dry_run(&self.tx_id, &self.tx_file);
+            info!("Contract id: {}", self.contract_id);
+            return;
+        }
+        let Some(receipt) = &self.receipt else {
+            log_propagated(&self.tx_id);
+            info!("Contract id: {}", self.contract_id);
+            return;
+        };
+        info!("Transaction id: {}", self.tx_id);
+        receipt.log();
+        match receipt.error {
+            Some(ref err) => info!("{} when deploying {}", err, self.contract_id),
+            None => {
+                info!("Deployment successful");
+                info!("Deployed contract id: {}", self.contract_id);
+            }
+        }
+        if let Some(verification) = &self.verification {
+            verification.log();
+        }
+    }
+
+    fn error(&self) -> Option<String> {
+        Receipt::error(&self.receipt).or_else(|| Verification::error(&self.verification))
+    }
+}
+
+/// Deployment reports of manifest contracts, by contract name
+pub type DeployReports = BTreeMap<String, DeployReport>;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ManifestReport {
+    pub results_path: PathBuf,
+    pub contracts: DeployReports,
+}
+
+impl Report for ManifestReport {
+    fn log(&self) {
+        for (name, report) in self.contracts.iter() {
+            match (report.error(), &report.receipt) {
+                (Some(err), _) => info!("{} when deploying {}", err, name),
+                (None, _) if report.already_deployed => warn!(
+                    "{} already deployed with contract id: {}",
+                    name, report.contract_id
+                ),
+                (None, Some(_)) => {
+                    info!("Deployed {} with contract id: {}", name, report.contract_id)
+                }
+                (None, None) if report.dry_run => info!(
+                    "{} accepted by preverification, contract id: {}",
+                    name, report.contract_id
+                ),
+                (None, None) => info!("{} propagated, contract id: {}", name, report.contract_id),
+            }
+        }
+        info!("Results written to {:?}", self.results_path);
+    }
+
+    fn error(&self) -> Option<String> {
+        self.contracts.iter().find_map(|(name, report)| {
+            report
+                .error()
+                .map(|err| format!("deployment of {} failed: {}", name, err))
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CallReport {
+    pub contract_id: String,
+    pub method: String,
+    pub tx_id: String,
+    /// Absent unless waiting for the transaction
+    #[serde(flatten)]
+    pub receipt: Option<Receipt>,
+    pub funding: Funding,
+    pub sender: String,
+    /// Transaction only preverified
+    pub dry_run: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tx_file: Option<PathBuf>,
+}
+
+impl Report for CallReport {
+    fn log(&self) {
+        if self.dry_run {
+            log_dry_run(&self.tx_id, &self.tx_file);
+            return;
+        }
+        let Some(receipt) = &self.receipt else {
+            log_propagated(&self.tx_id);
+            return;
+        };
+        info!("Transaction id: {}", self.tx_id);
+        receipt.log();
+        match receipt.error {
+            Some(ref err) => info!("Contract error when calling {}: {}", self.method, err),
+            None => info!("Call to {} successful", self.method),
+        }
+    }
+
+    fn error(&self) -> Option<String> {
+        Receipt::error(&self.receipt)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct QueryReport {
+    pub contract_id: String,
+    pub method: String,
+    /// Result in the requested format, absent when written to a file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub out_file: Option<PathBuf>,
+    pub size: usize,
+}
+
+impl Report for QueryReport {
+    fn log(&self) {
+        match (&self.result, &self.out_file) {
+            (Some(Value::String(s)), _) => println!("{}", s),
+            (Some(value), _) => println!(
+                "{}",
+                serde_json::to_string_pretty(value).unwrap_or_default()
+            ),
+            (None, Some(out_file)) => info!("{} bytes written to {:?}", self.size, out_file),
+            (None, None) => {}
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BalanceReport {
+    pub funding: Funding,
+    pub address: String,
+    pub balance: u64,
+    /// Maximum amount spendable in a single Phoenix transaction
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub spendable: Option<u64>,
+    /// Moonlight account nonce
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<u64>,
+}
+
+impl Report for BalanceReport {
+    fn log(&self) {
+        match (self.spendable, self.nonce) {
+            (Some(spendable), _) => info!("Balance: {} (spendable {})", self.balance, spendable),
+            (_, Some(nonce)) => info!("Balance: {} (nonce {})", self.balance, nonce),
+            _ => info!("Balance: {}", self.balance),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TransferReport {
+    pub tx_id: String,
+    /// Absent unless waiting for the transaction
+    #[serde(flatten)]
+    pub receipt: Option<Receipt>,
+    pub funding: Funding,
+    pub sender: String,
+    pub receiver: String,
+    pub amount: u64,
+    /// Transaction only preverified
+    pub dry_run: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tx_file: Option<PathBuf>,
+}
+
+impl Report for TransferReport {
+    fn log(&self) {
+        if self.dry_run {
+            log_dry_run(&self.tx_id, &self.tx_file);
+            return;
+        }
+        let Some(receipt) = &self.receipt else {
+            log_propagated(&self.tx_id);
+            return;
+        };
+        info!("Transaction id: {}", self.tx_id);
+        receipt.log();
+        match receipt.error {
+            Some(ref err) => info!("Transfer of {} failed: {}", self.amount, err),
+            None => info!("Transfer of {} successful", self.amount),
+        }
+    }
+
+    fn error(&self) -> Option<String> {
+        Receipt::error(&self.receipt)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TxStatusReport {
+    pub tx_id: String,
+    #[serde(flatten)]
+    pub receipt: Receipt,
+}
+
+impl Report for TxStatusReport {
+    fn log(&self) {
+        self.receipt.log();
+        match self.receipt.error {
+            Some(ref err) => info!("Transaction {} failed: {}", self.tx_id, err),
+            None => info!("Transaction {} executed successfully", self.tx_id),
+        }
+    }
+
+    fn error(&self) -> Option<String> {
+        self.receipt.error.clone()
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportReport {
+    pub funding: Funding,
+    /// Base 58 Moonlight account or Phoenix view key the state is exported for
+    pub key: String,
+    pub chain_id: u8,
+    /// Moonlight account balance, or total value of the exported notes
+    pub balance: u64,
+    /// Number of exported Phoenix notes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notes: Option<usize>,
+    pub out_file: PathBuf,
+}
+
+impl Report for ExportReport {
+    fn log(&self) {
+        match self.notes {
+            Some(notes) => info!("Exported {} notes of value {}", notes, self.balance),
+            None => info!("Exported account with balance {}", self.balance),
+        }
+        info!("State written to {:?}", self.out_file);
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SignReport {
+    pub tx_id: String,
+    pub funding: Funding,
+    pub sender: String,
+    /// Id of the contract deployed by the transaction
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub contract_id: Option<String>,
+    pub out_file: PathBuf,
+}
+
+impl Report for SignReport {
+    fn log(&self) {
+        info!("Transaction id: {}", self.tx_id);
+        if let Some(contract_id) = &self.contract_id {
+            info!("Contract id: {}", contract_id);
+        }
+        info!("Signed transaction written to {:?}", self.out_file);
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BroadcastReport {
+    pub tx_id: String,
+    /// Absent unless waiting for the transaction
+    #[serde(flatten)]
+    pub receipt: Option<Receipt>,
+    pub dry_run: bool,
+}
+
+impl Report for BroadcastReport {
+    fn log(&self) {
+        if self.dry_run {
+            log_dry_run(&self.tx_id, &None);
+            return;
+        }
+        let Some(receipt) = &self.receipt else {
+            log_propagated(&self.tx_id);
+            return;
+        };
+        info!("Transaction id: {}", self.tx_id);
+        receipt.log();
+        match receipt.error {
+            Some(ref err) => info!("Transaction {} failed: {}", self.tx_id, err),
+            None => info!("Transaction {} executed successfully", self.tx_id),
+        }
+    }
+
+    fn error(&self) -> Option<String> {
+        Receipt::error(&self.receipt)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EstimateReport {
+    pub gas_spent: u64,
+    /// Recommended gas limit, gas spent increased by the safety margin
+    pub gas_limit: u64,
+    /// Safety margin, in percent
+    pub gas_margin: u64,
+    pub funding: Funding,
+    /// Id of the contract the simulated transaction deploys
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub contract_id: Option<String>,
+    pub error: Option<String>,
+}
+
+impl Report for EstimateReport {
+    fn log(&self) {
+        match self.error {
+            Some(ref err) => info!("Simulated transaction failed: {}", err),
+            None => {
+                info!("Gas spent: {}", self.gas_spent);
+                info!(
+                    "Recommended gas limit: {} ({}% margin)",
+                    self.gas_limit, self.gas_margin
+                );
+            }
+        }
+    }
+
+    fn error(&self) -> Option<String> {
+        self.error.clone()
+    }
+}
+
+/// Keys derived at an index
+#[derive(Debug, Clone, Serialize)]
+pub struct Profile {
+    pub index: u64,
+    /// Base 58 Phoenix public key, absent for a single Moonlight key
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub phoenix: Option<String>,
+    /// Base 58 Phoenix view key, needed to export notes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub view_key: Option<String>,
+    /// Base 58 Moonlight account public key
+    pub moonlight: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AddressesReport {
+    pub funding: Funding,
+    /// Index of the profile funding transactions
+    pub profile_index: u64,
+    pub profiles: Vec<Profile>,
+}
+
+impl Report for AddressesReport {
+    fn log(&self) {
+        for profile in self.profiles.iter() {
+            let funding = if profile.index == self.profile_index {
+                format!(" (funding from {})", self.funding)
+            } else {
+                String::new()
+            };
+            info!("Profile {}{}", profile.index, funding);
+            if let Some(phoenix) = &profile.phoenix {
+                info!("  Phoenix address: {}", phoenix);
+            }
+            if let Some(view_key) = &profile.view_key {
+                info!("  View key: {}", view_key);
+            }
+            info!("  Moonlight account: {}", profile.moonlight);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VanityMatch {
+    pub nonce: u64,
+    pub contract_id: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VanityReport {
+    /// Matching nonces in ascending order
+    pub matches: Vec<VanityMatch>,
+    /// Number of nonces tried
+    pub iterations: u64,
+    /// Deployment with the first nonce found, if requested
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deployment: Option<DeployReport>,
+}
+
+impl Report for VanityReport {
+    fn log(&self) {
+        info!("Tried {} nonces", self.iterations);
+        for m in self.matches.iter() {
+            info!("Nonce {}: {}", m.nonce, m.contract_id);
+        }
+        if let Some(deployment) = &self.deployment {
+            deployment.log();
+        }
+    }
+
+    fn error(&self) -> Option<String> {
+        if self.matches.is_empty() {
+            return Some(format!(
+                "no matching contract id found in {} nonces",
+                self.iterations
+            ));
+        }
+        self.deployment.as_ref().and_then(|d| d.error())
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ContractIdEntry {
+    /// Name of the manifest contract
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub owner: String,
+    pub nonce: u64,
+    pub bytecode_hash: String,
+    pub contract_id: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ContractIdReport {
+    pub contracts: Vec<ContractIdEntry>,
+}
+
+impl Report for ContractIdReport {
+    fn log(&self) {
+        let width = self
+            .contracts
+            .iter()
+            .filter_map(|entry| entry.name.as_ref().map(String::len))
+            .max();
+        for entry in self.contracts.iter() {
+            match (&entry.name, width) {
+                (Some(name), Some(width)) => info!(
+                    "{:width$}  {:>20}  {}",
+                    name,
+                    entry.nonce,
+                    entry.contract_id,
+                    width = width
+                ),
+                _ => info!("{:>20}  {}", entry.nonce, entry.contract_id),
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct KeystoreReport {
+    pub path: PathBuf,
+    pub kind: SecretKind,
+    /// Recovery phrase of a created keystore, or exported secret
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+    /// Block height scanning for notes starts from
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub birthday: Option<u64>,
+}
+
+impl Report for KeystoreReport {
+    fn log(&self) {
+        match (&self.secret, self.kind) {
+            (Some(secret), SecretKind::Phrase) => info!("Recovery phrase: {}", secret),
+            (Some(secret), SecretKind::Moonlight) => info!("Moonlight secret key: {}", secret),
+            (None, _) => {}
+        }
+        if let Some(birthday) = self.birthday {
+            info!("Birthday height: {}", birthday);
+        }
+        info!("Keystore: {:?}", self.path);
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RescanReport {
+    pub address: String,
+    pub from_height: u64,
+    /// Notes found, spent or not
+    pub notes: usize,
+    pub balance: u64,
+    pub spendable: u64,
+}
+
+impl Report for RescanReport {
+    fn log(&self) {
+        info!(
+            "Found {} notes scanning from height {}",
+            self.notes, self.from_height
+        );
+        info!("Balance: {} (spendable {})", self.balance, self.spendable);
+    }
+}
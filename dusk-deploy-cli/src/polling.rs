@@ -0,0 +1,120 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use crate::args::WaitFor;
+use crate::Error;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Longest interval or timeout, in seconds, beyond which waiting is
+/// pointless
+const MAX_SECS: f64 = 365.0 * 24.0 * 3600.0;
+
+/// Polling of the node while waiting for a transaction, intervals growing
+/// by the backoff factor after every attempt
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Polling {
+    /// Initial interval between attempts, in seconds
+    #[serde(deserialize_with = "deserialize_non_negative")]
+    pub interval: f64,
+    /// Factor the interval is multiplied by after every attempt
+    #[serde(deserialize_with = "deserialize_non_negative")]
+    pub backoff: f64,
+    /// Time after which waiting is given up, in seconds
+    #[serde(deserialize_with = "deserialize_non_negative")]
+    pub timeout: f64,
+}
+
+impl Default for Polling {
+    fn default() -> Self {
+        Self {
+            interval: 3.0,
+            backoff: 1.0,
+            timeout: 60.0,
+        }
+    }
+}
+
+impl Polling {
+    /// Calls `attempt` until it returns a value, failing with a timeout
+    /// error mentioning `what` is waited for
+    pub fn poll<T>(
+        &self,
+        what: &str,
+        mut attempt: impl FnMut() -> Result<Option<T>, Error>,
+    ) -> Result<T, Error> {
+        let start = Instant::now();
+        let timeout = duration(self.timeout);
+        let mut interval = self.interval.max(0.0);
+        loop {
+            if let Some(value) = attempt()? {
+                return Ok(value);
+            }
+            let elapsed = start.elapsed();
+            if elapsed >= timeout {
+                return Err(Error::Propagate(
+                    format!("Transaction timed out waiting for {}", what).into(),
+                ));
+            }
+            thread::sleep(duration(interval).min(timeout - elapsed));
+            interval *= self.backoff.max(1.0);
+        }
+    }
+}
+
+/// Checks that a number of seconds or a factor is finite and not negative
+pub fn non_negative(value: f64) -> Result<f64, String> {
+    if value.is_finite() && value >= 0.0 {
+        Ok(value)
+    } else {
+        Err(format!(
+            "expected a finite, non-negative number, found `{}`",
+            value
+        ))
+    }
+}
+
+fn deserialize_non_negative<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+    non_negative(f64::deserialize(deserializer)?).map_err(serde::de::Error::custom)
+}
+
+/// Duration of the given number of seconds, capped to [`MAX_SECS`]
+fn duration(secs: f64) -> Duration {
+    Duration::try_from_secs_f64(secs.clamp(0.0, MAX_SECS)).unwrap_or_default()
+}
+
+/// What to wait for after propagating a transaction, and how
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WaitPolicy {
+    pub wait_for: WaitFor,
+    pub polling: Polling,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_config_values_are_rejected() {
+        let polling: Polling = toml::from_str("interval = 0.5\ntimeout = 10").unwrap();
+        assert_eq!(polling.interval, 0.5);
+        assert_eq!(polling.backoff, 1.0);
+        assert_eq!(polling.timeout, 10.0);
+        for config in ["interval = -1.0", "backoff = nan", "timeout = inf"] {
+            assert!(toml::from_str::<Polling>(config).is_err(), "{}", config);
+        }
+    }
+
+    #[test]
+    fn durations_are_capped() {
+        assert_eq!(duration(1.5), Duration::from_millis(1500));
+        assert_eq!(duration(-1.0), Duration::ZERO);
+        assert_eq!(duration(f64::INFINITY), Duration::from_secs_f64(MAX_SECS));
+        assert_eq!(duration(f64::MAX), Duration::from_secs_f64(MAX_SECS));
+    }
+}
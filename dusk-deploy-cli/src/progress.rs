@@ -0,0 +1,257 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use parking_lot::Mutex;
+use std::io::{IsTerminal, Write};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::info;
+
+/// Interval between progress bar updates
+const BAR_INTERVAL: Duration = Duration::from_millis(200);
+/// Interval between progress log lines, when stderr is not a terminal
+const LOG_INTERVAL: Duration = Duration::from_secs(10);
+const BAR_WIDTH: usize = 30;
+/// Number of leaves scanned between progress reports
+const PROGRESS_LEAVES: u64 = 1000;
+
+/// Callback the state client reports scanning progress to
+pub type ProgressCallback = Arc<dyn Fn(&ScanProgress) + Send + Sync>;
+
+/// Progress of a scan of the leaves of the transfer contract
+#[derive(Debug, Clone)]
+pub struct ScanProgress {
+    /// Block height scanning started from
+    pub start_height: u64,
+    /// Block height of the last leaf scanned
+    pub height: u64,
+    /// Block height of the tip when scanning started
+    pub tip_height: u64,
+    /// Number of leaves scanned
+    pub leaves: u64,
+    pub elapsed: Duration,
+    /// True once all leaves are scanned
+    pub done: bool,
+}
+
+impl ScanProgress {
+    /// Fraction of the blocks scanned, between 0 and 1
+    pub fn fraction(&self) -> f64 {
+        if self.done || self.tip_height <= self.start_height {
+            return 1.0;
+        }
+        let scanned = self.height.saturating_sub(self.start_height) as f64;
+        (scanned / (self.tip_height - self.start_height) as f64).min(1.0)
+    }
+
+    /// Leaves scanned per second
+    pub fn throughput(&self) -> f64 {
+        let secs = self.elapsed.as_secs_f64();
+        if secs > 0.0 {
+            self.leaves as f64 / secs
+        } else {
+            0.0
+        }
+    }
+
+    /// Estimated time left, assuming blocks hold the same number of leaves
+    pub fn eta(&self) -> Option<Duration> {
+        let fraction = self.fraction();
+        if fraction <= 0.0 {
+            return None;
+        }
+        let secs = self.elapsed.as_secs_f64() * (1.0 - fraction) / fraction;
+        Some(Duration::from_secs_f64(secs))
+    }
+}
+
+/// Tracks a scan, reporting its progress to the callback every
+/// `PROGRESS_LEAVES` leaves and once done
+pub struct ProgressTracker {
+    callback: Option<ProgressCallback>,
+    progress: ScanProgress,
+    started: Instant,
+}
+
+impl ProgressTracker {
+    pub fn new(callback: Option<ProgressCallback>, start_height: u64, tip_height: u64) -> Self {
+        Self {
+            callback,
+            progress: ScanProgress {
+                start_height,
+                height: start_height,
+                tip_height,
+                leaves: 0,
+                elapsed: Duration::default(),
+                done: false,
+            },
+            started: Instant::now(),
+        }
+    }
+
+    /// Records a leaf scanned, of a block at the given height
+    pub fn leaf(&mut self, block_height: u64) {
+        self.progress.leaves += 1;
+        // reported progress never goes backwards
+        self.progress.height = self.progress.height.max(block_height);
+        if self.progress.leaves % PROGRESS_LEAVES == 0 {
+            self.report();
+        }
+    }
+
+    /// Reports the scan done
+    pub fn done(mut self) {
+        self.progress.done = true;
+        self.report();
+    }
+
+    fn report(&mut self) {
+        if let Some(callback) = &self.callback {
+            self.progress.elapsed = self.started.elapsed();
+            callback(&self.progress);
+        }
+    }
+}
+
+/// Renders scanning progress as a progress bar on stderr when it is a
+/// terminal, as periodic log lines otherwise
+pub struct ProgressDisplay {
+    tty: bool,
+    last_update: Mutex<Option<Instant>>,
+}
+
+impl ProgressDisplay {
+    pub fn callback() -> ProgressCallback {
+        let display = Self {
+            tty: std::io::stderr().is_terminal(),
+            last_update: Mutex::new(None),
+        };
+        Arc::new(move |progress| display.show(progress))
+    }
+
+    fn show(&self, progress: &ScanProgress) {
+        let interval = if self.tty { BAR_INTERVAL } else { LOG_INTERVAL };
+        let mut last_update = self.last_update.lock();
+        if !progress.done && last_update.is_some_and(|last| last.elapsed() < interval) {
+            return;
+        }
+        *last_update = Some(Instant::now());
+
+        let eta = progress
+            .eta()
+            .map(|eta| format!("{}s", eta.as_secs()))
+            .unwrap_or_else(|| String::from("?"));
+        let status = format!(
+            "height {}/{}, {} leaves, {:.0} leaves/s, ETA {}",
+            progress.height,
+            progress.tip_height,
+            progress.leaves,
+            progress.throughput(),
+            eta
+        );
+        if self.tty {
+            let filled = (progress.fraction() * BAR_WIDTH as f64) as usize;
+            let mut stderr = std::io::stderr().lock();
+            let _ = write!(
+                stderr,
+                "\r[{}{}] {:>3.0}% {}",
+                "#".repeat(filled),
+                " ".repeat(BAR_WIDTH - filled),
+                progress.fraction() * 100.0,
+                status
+            );
+            if progress.done {
+                let _ = writeln!(stderr);
+            }
+            let _ = stderr.flush();
+        } else {
+            info!(
+                "Scanning notes: {:.0}%, {}",
+                progress.fraction() * 100.0,
+                status
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn progress(start_height: u64, height: u64, tip_height: u64) -> ScanProgress {
+        ScanProgress {
+            start_height,
+            height,
+            tip_height,
+            leaves: 0,
+            elapsed: Duration::from_secs(10),
+            done: false,
+        }
+    }
+
+    #[test]
+    fn fractions_and_eta() {
+        assert_eq!(progress(100, 100, 200).fraction(), 0.0);
+        assert_eq!(progress(100, 150, 200).fraction(), 0.5);
+        assert_eq!(progress(100, 300, 200).fraction(), 1.0);
+        assert_eq!(progress(100, 50, 200).fraction(), 0.0);
+        // nothing to scan
+        assert_eq!(progress(200, 200, 200).fraction(), 1.0);
+        assert_eq!(progress(300, 300, 200).fraction(), 1.0);
+
+        assert_eq!(progress(100, 100, 200).eta(), None);
+        assert_eq!(progress(100, 125, 200).eta(), Some(Duration::from_secs(30)));
+        assert_eq!(progress(100, 200, 200).eta(), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn progress_is_reported_within_bounds() {
+        let reports = Arc::new(Mutex::new(vec![]));
+        let callback: ProgressCallback = {
+            let reports = reports.clone();
+            Arc::new(move |progress: &ScanProgress| reports.lock().push(progress.clone()))
+        };
+
+        let mut tracker = ProgressTracker::new(Some(callback), 100, 300);
+        // ten leaves per block, past the tip, one of them out of order
+        for i in 0..2500 {
+            tracker.leaf(if i == 1999 { 100 } else { 100 + i / 10 });
+        }
+        tracker.done();
+
+        let reports = reports.lock();
+        let leaves: Vec<_> = reports.iter().map(|p| p.leaves).collect();
+        assert_eq!(leaves, [1000, 2000, 2500]);
+        let done: Vec<_> = reports.iter().map(|p| p.done).collect();
+        assert_eq!(done, [false, false, true]);
+        let heights: Vec<_> = reports.iter().map(|p| p.height).collect();
+        assert_eq!(heights, [199, 299, 349]);
+
+        let fractions: Vec<_> = reports.iter().map(ScanProgress::fraction).collect();
+        assert!(fractions.iter().all(|f| (0.0..=1.0).contains(f)));
+        assert!(fractions.windows(2).all(|w| w[0] <= w[1]));
+        assert_eq!(fractions.last(), Some(&1.0));
+    }
+
+    #[test]
+    fn done_is_reported_without_leaves() {
+        let reports = Arc::new(Mutex::new(vec![]));
+        let callback: ProgressCallback = {
+            let reports = reports.clone();
+            Arc::new(move |progress: &ScanProgress| reports.lock().push(progress.clone()))
+        };
+        ProgressTracker::new(Some(callback), 100, 100).done();
+        let reports = reports.lock();
+        assert_eq!(reports.len(), 1);
+        assert!(reports[0].done);
+        assert_eq!(reports[0].fraction(), 1.0);
+
+        // no callback, nothing reported
+        let mut tracker = ProgressTracker::new(None, 0, 10);
+        tracker.leaf(5);
+        tracker.done();
+    }
+}
@@ -0,0 +1,372 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use crate::type_desc::TypeDesc;
+use crate::Error;
+use bytecheck::CheckBytes;
+use dusk_bytes::Serializable;
+use execution_core::signatures::bls::PublicKey as BlsPublicKey;
+use execution_core::transfer::phoenix::PublicKey as PhoenixPublicKey;
+use rkyv::de::deserializers::SharedDeserializeMap;
+use rkyv::validation::validators::DefaultValidator;
+use rkyv::{AlignedVec, Archive, Deserialize};
+use serde_json::{Map, Value};
+use std::borrow::Cow;
+
+/// Maximum length of a string stored inline in its archived form
+const STRING_INLINE_CAPACITY: usize = 7;
+
+/// Decodes rkyv serializations of the types given by a [`TypeDesc`] into
+/// JSON values, following the conventions of
+/// [`ArgsEncoder`](crate::args_encoder::ArgsEncoder).
+///
+/// The archived root is validated while decoding: all relative pointers
+/// must point to properly aligned data located before the value holding
+/// them, bools and option tags must be valid and strings must be UTF-8.
+pub struct ResultDecoder<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> ResultDecoder<'a> {
+    pub fn decode(desc: &TypeDesc, bytes: &'a [u8]) -> Result<Value, Error> {
+        let layout = desc.layout()?;
+        if bytes.len() < layout.size {
+            return Err(decoding_err(format!(
+                "expected at least {} bytes, found {}",
+                layout.size,
+                bytes.len()
+            )));
+        }
+        let pos = bytes.len() - layout.size;
+        if pos % layout.align != 0 {
+            return Err(decoding_err(format!("root at {} is not aligned", pos)));
+        }
+        ResultDecoder { bytes }.value(desc, pos)
+    }
+
+    fn value(&self, desc: &TypeDesc, pos: usize) -> Result<Value, Error> {
+        Ok(match desc {
+            TypeDesc::U8 => Value::from(self.uint(pos, 1) as u8),
+            TypeDesc::U16 => Value::from(self.uint(pos, 2) as u16),
+            TypeDesc::U32 => Value::from(self.uint(pos, 4) as u32),
+            TypeDesc::U64 => Value::from(self.uint(pos, 8) as u64),
+            TypeDesc::U128 => Value::from(self.uint(pos, 16).to_string()),
+            TypeDesc::I8 => Value::from(self.uint(pos, 1) as i8),
+            TypeDesc::I16 => Value::from(self.uint(pos, 2) as i16),
+            TypeDesc::I32 => Value::from(self.uint(pos, 4) as i32),
+            TypeDesc::I64 => Value::from(self.uint(pos, 8) as i64),
+            TypeDesc::I128 => Value::from((self.uint(pos, 16) as i128).to_string()),
+            TypeDesc::Bool => match self.bytes[pos] {
+                0 => Value::Bool(false),
+                1 => Value::Bool(true),
+                b => return Err(decoding_err(format!("invalid bool {} at {}", b, pos))),
+            },
+            TypeDesc::String => {
+                let bytes = self.string_bytes(pos)?;
+                let s = std::str::from_utf8(bytes)
+                    .map_err(|_| decoding_err(format!("invalid UTF-8 string at {}", pos)))?;
+                Value::from(s)
+            }
+            TypeDesc::BlsPublicKey => {
+                let pk: BlsPublicKey = self.plain(desc, pos)?;
+                Value::from(bs58::encode(pk.to_bytes()).into_string())
+            }
+            TypeDesc::PhoenixPublicKey => {
+                let pk: PhoenixPublicKey = self.plain(desc, pos)?;
+                Value::from(bs58::encode(pk.to_bytes()).into_string())
+            }
+            TypeDesc::Array(t, n) => self.elements(t, pos, *n)?,
+            TypeDesc::Vec(t) => {
+                let len = self.uint(pos + 4, 4) as usize;
+                let target = self.target(pos, t, len)?;
+                self.elements(t, target, len)?
+            }
+            TypeDesc::Option(t) => match self.bytes[pos] {
+                0 => Value::Null,
+                1 => self.value(t, pos + TypeDesc::option_value_offset(t)?)?,
+                b => return Err(decoding_err(format!("invalid option tag {} at {}", b, pos))),
+            },
+            TypeDesc::Tuple(types, _) => {
+                let offsets = desc.layout()?.offsets;
+                Value::Array(
+                    types
+                        .iter()
+                        .zip(offsets)
+                        .map(|(t, offset)| self.value(t, pos + offset))
+                        .collect::<Result<_, _>>()?,
+                )
+            }
+            TypeDesc::Struct { fields, .. } => {
+                let offsets = desc.layout()?.offsets;
+                let mut map = Map::new();
+                for ((name, t), offset) in fields.iter().zip(offsets) {
+                    map.insert(name.clone(), self.value(t, pos + offset)?);
+                }
+                Value::Object(map)
+            }
+        })
+    }
+
+    /// Reads a little endian integer of the given size
+    fn uint(&self, pos: usize, size: usize) -> u128 {
+        let mut buf = [0u8; 16];
+        buf[..size].copy_from_slice(&self.bytes[pos..pos + size]);
+        u128::from_le_bytes(buf)
+    }
+
+    fn rel_offset(&self, pos: usize) -> i64 {
+        self.uint(pos, 4) as u32 as i32 as i64
+    }
+
+    /// Checks the relative pointer at `pos` and returns the position of the
+    /// `len` elements of type `elem` it points to
+    fn target(&self, pos: usize, elem: &TypeDesc, len: usize) -> Result<usize, Error> {
+        let layout = elem.layout()?;
+        // zero sized elements take no space, their number is still bounded
+        // to keep corrupted lengths from exhausting memory
+        if layout.size == 0 && len > self.bytes.len() {
+            return Err(decoding_err(format!("invalid length {} at {}", len, pos)));
+        }
+        let target = pos as i64 + self.rel_offset(pos);
+        let end = layout
+            .size
+            .checked_mul(len)
+            .and_then(|size| i64::try_from(size).ok())
+            .and_then(|size| target.checked_add(size));
+        if target < 0 || !matches!(end, Some(end) if end <= pos as i64) {
+            return Err(decoding_err(format!(
+                "relative pointer at {} out of bounds",
+                pos
+            )));
+        }
+        let target = target as usize;
+        if target % layout.align != 0 {
+            return Err(decoding_err(format!(
+                "relative pointer at {} is not aligned",
+                pos
+            )));
+        }
+        Ok(target)
+    }
+
+    fn string_bytes(&self, pos: usize) -> Result<&'a [u8], Error> {
+        let inline_len = self.bytes[pos + STRING_INLINE_CAPACITY];
+        if inline_len & 0x80 == 0 {
+            let inline_len = inline_len as usize;
+            if inline_len > STRING_INLINE_CAPACITY {
+                return Err(decoding_err(format!("invalid string length at {}", pos)));
+            }
+            return Ok(&self.bytes[pos..pos + inline_len]);
+        }
+        let len = self.uint(pos, 4) as usize;
+        let target = pos as i64 + self.rel_offset(pos + 4);
+        if target < 0 || target + len as i64 > pos as i64 {
+            return Err(decoding_err(format!("string at {} out of bounds", pos)));
+        }
+        Ok(&self.bytes[target as usize..target as usize + len])
+    }
+
+    fn elements(&self, elem: &TypeDesc, pos: usize, len: usize) -> Result<Value, Error> {
+        let size = elem.layout()?.size;
+        if *elem == TypeDesc::U8 {
+            return Ok(Value::from(hex::encode(&self.bytes[pos..pos + len])));
+        }
+        Ok(Value::Array(
+            (0..len)
+                .map(|i| self.value(elem, pos + i * size))
+                .collect::<Result<_, _>>()?,
+        ))
+    }
+
+    /// Deserializes a plain type, which contains no relative pointers and
+    /// hence can be validated on its own
+    fn plain<T>(&self, desc: &TypeDesc, pos: usize) -> Result<T, Error>
+    where
+        T: Archive,
+        for<'b> T::Archived:
+            'b + CheckBytes<DefaultValidator<'b>> + Deserialize<T, SharedDeserializeMap>,
+    {
+        let mut aligned = AlignedVec::new();
+        aligned.extend_from_slice(&self.bytes[pos..pos + desc.layout()?.size]);
+        rkyv::from_bytes::<T>(&aligned)
+            .map_err(|_| decoding_err(format!("invalid {} at {}", desc, pos)))
+    }
+}
+
+fn decoding_err(msg: impl Into<Cow<'static, str>>) -> Error {
+    Error::Decoding(msg.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::args_encoder::ArgsEncoder;
+    use rkyv::ser::serializers::AllocSerializer;
+    use serde_json::json;
+
+    #[derive(rkyv::Archive, rkyv::Serialize)]
+    #[archive_attr(repr(C))]
+    struct Entry {
+        active: bool,
+        amount: u64,
+        tags: Vec<String>,
+        limit: Option<u32>,
+    }
+
+    fn decode(desc: &str, bytes: &[u8]) -> Result<Value, Error> {
+        let desc: TypeDesc = desc.parse().unwrap();
+        // archived roots are read from aligned buffers
+        let mut aligned = AlignedVec::new();
+        aligned.extend_from_slice(bytes);
+        ResultDecoder::decode(&desc, &aligned)
+    }
+
+    fn assert_decodes<T>(desc: &str, value: &T, expected: Value)
+    where
+        T: rkyv::Serialize<AllocSerializer<256>>,
+    {
+        let bytes = rkyv::to_bytes::<_, 256>(value).unwrap();
+        assert_eq!(decode(desc, &bytes).unwrap(), expected, "{}", desc);
+    }
+
+    fn assert_fails(desc: &str, bytes: &[u8]) {
+        match decode(desc, bytes) {
+            Err(Error::Decoding(_)) => {}
+            result => panic!("{} {:?}: {:?}", desc, bytes, result),
+        }
+    }
+
+    #[test]
+    fn scalars() {
+        assert_decodes("u8", &7u8, json!(7));
+        assert_decodes("u16", &513u16, json!(513));
+        assert_decodes("u32", &u32::MAX, json!(u32::MAX));
+        assert_decodes("u64", &u64::MAX, json!(u64::MAX));
+        assert_decodes("u128", &u128::MAX, json!(u128::MAX.to_string()));
+        assert_decodes("i8", &-1i8, json!(-1));
+        assert_decodes("i16", &i16::MIN, json!(i16::MIN));
+        assert_decodes("i32", &-70000i32, json!(-70000));
+        assert_decodes("i64", &i64::MIN, json!(i64::MIN));
+        assert_decodes("i128", &i128::MIN, json!(i128::MIN.to_string()));
+        assert_decodes("bool", &false, json!(false));
+        assert_decodes("()", &(), json!([]));
+    }
+
+    #[test]
+    fn strings_and_collections() {
+        for s in ["", "seven c", "eight ch", "a string stored out of line"] {
+            assert_decodes("String", &String::from(s), json!(s));
+        }
+        assert_decodes("Vec<u8>", &vec![1u8, 255], json!("01ff"));
+        assert_decodes("[u8; 3]", &[1u8, 2, 3], json!("010203"));
+        assert_decodes("Vec<u32>", &vec![1u32, 2], json!([1, 2]));
+        assert_decodes("Vec<u64>", &Vec::<u64>::new(), json!([]));
+        assert_decodes(
+            "Vec<Vec<String>>",
+            &vec![
+                vec![String::from("a")],
+                vec![String::from("out of line string")],
+            ],
+            json!([["a"], ["out of line string"]]),
+        );
+        assert_decodes("[Option<u16>; 2]", &[Some(1u16), None], json!([1, null]));
+        assert_decodes("(u8, u64)", &(1u8, 2u64), json!([1, 2]));
+        assert_decodes(
+            "Option<(String, u32)>",
+            &Some((String::from("out of line string"), 3u32)),
+            json!(["out of line string", 3]),
+        );
+    }
+
+    #[test]
+    fn structs() {
+        let entry = Entry {
+            active: true,
+            amount: 5,
+            tags: vec![String::from("a"), String::from("out of line tag")],
+            limit: Some(u32::MAX),
+        };
+        assert_decodes(
+            "Vec<repr(C) {active: bool, amount: u64, tags: Vec<String>, limit: Option<u32>}>",
+            &vec![entry],
+            json!([{
+                "active": true,
+                "amount": 5,
+                "tags": ["a", "out of line tag"],
+                "limit": u32::MAX
+            }]),
+        );
+    }
+
+    #[test]
+    fn encoded_arguments_decode_back() {
+        for (desc, value) in [
+            (
+                "Vec<(String, Option<u64>)>",
+                json!([["a", 1], ["out of line string", null]]),
+            ),
+            (
+                "{a: u8 @ 8, b: u64 @ 0, c: Vec<bool> @ 12}",
+                json!({"a": 1, "b": 2, "c": [true]}),
+            ),
+            ("(i16, u16, String)", json!([-3, 4, "xyz"])),
+        ] {
+            let bytes = ArgsEncoder::encode(&desc.parse().unwrap(), &value).unwrap();
+            assert_eq!(decode(desc, &bytes).unwrap(), value, "{}", desc);
+        }
+    }
+
+    #[test]
+    fn truncated_buffers() {
+        assert_fails("u64", &[]);
+        assert_fails("u64", &[0; 7]);
+        assert_fails("(u8, u64)", &[0; 8]);
+        // relative pointers then point before the start of the buffer
+        let bytes = rkyv::to_bytes::<_, 256>(&String::from("out of line string")).unwrap();
+        assert_fails("String", &bytes[4..]);
+        let bytes = rkyv::to_bytes::<_, 256>(&vec![1u32, 2, 3]).unwrap();
+        assert_fails("Vec<u32>", &bytes[4..]);
+        // the root is then not aligned
+        let bytes = rkyv::to_bytes::<_, 256>(&vec![1u64]).unwrap();
+        assert_fails("(u32, Vec<u64>)", &bytes[1..]);
+    }
+
+    #[test]
+    fn malformed_buffers() {
+        assert_fails("bool", &[2]);
+        assert_fails("Option<u8>", &[2, 0]);
+        // inline string with an invalid length, then invalid UTF-8
+        assert_fails("String", &[0, 0, 0, 0, 0, 0, 0, 8]);
+        assert_fails("String", &[0xff, 0, 0, 0, 0, 0, 0, 1]);
+
+        let bytes = rkyv::to_bytes::<_, 256>(&vec![1u32]).unwrap().to_vec();
+        let len = bytes.len();
+        // length larger than the data before the vector
+        let mut longer = bytes.clone();
+        longer[len - 4..].copy_from_slice(&2u32.to_le_bytes());
+        assert_fails("Vec<u32>", &longer);
+        // pointer to the vector itself, then to an unaligned position
+        let mut forward = bytes.clone();
+        forward[len - 8..len - 4].copy_from_slice(&0i32.to_le_bytes());
+        assert_fails("Vec<u32>", &forward);
+        let mut unaligned = bytes;
+        unaligned[len - 8..len - 4].copy_from_slice(&(-6i32).to_le_bytes());
+        assert_fails("Vec<u32>", &unaligned);
+
+        // zero sized elements with a length exceeding the buffer size
+        let mut zero_sized = [0u8; 8];
+        zero_sized[4..].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_fails("Vec<()>", &zero_sized);
+
+        // huge lengths of large elements, pointing to the start of the buffer
+        let mut huge = [0u8; 16];
+        huge[8..12].copy_from_slice(&(-8i32).to_le_bytes());
+        huge[12..].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_fails("Vec<u128>", &huge);
+        assert_fails("Vec<[u64; 2097152]>", &huge);
+        assert_fails("Vec<Vec<[u8; 16777216]>>", &huge);
+    }
+}
@@ -0,0 +1,221 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use crate::note_cache::NoteCache;
+use execution_core::transfer::phoenix::{NoteLeaf, ViewKey};
+use std::thread;
+use wallet::EnrichedNote;
+
+/// Number of leaves decoded from the stream before they are tested
+const BATCH_LEAVES: usize = 4096;
+
+pub struct Scanner;
+
+impl Scanner {
+    /// Tests leaves against all view keys, splitting them across the
+    /// available cores, and appends the notes owned by every view key to its
+    /// vector of `notes`, in the order of the leaves
+    pub fn find_owned(leaves: &[NoteLeaf], vks: &[ViewKey], notes: &mut [Vec<EnrichedNote>]) {
+        let threads = thread::available_parallelism()
+            .map(usize::from)
+            .unwrap_or(1);
+        let chunk_size = leaves.len().div_ceil(threads).max(1);
+
+        thread::scope(|s| {
+            let handles: Vec<_> = leaves
+                .chunks(chunk_size)
+                .map(|chunk| s.spawn(move || Self::owned_in_chunk(chunk, vks)))
+                .collect();
+
+            for handle in handles {
+                let owned = handle.join().expect("scanning thread should not panic");
+                for (vk_notes, owned) in notes.iter_mut().zip(owned) {
+                    vk_notes.extend(owned);
+                }
+            }
+        })
+    }
+
+    fn owned_in_chunk(leaves: &[NoteLeaf], vks: &[ViewKey]) -> Vec<Vec<EnrichedNote>> {
+        let mut notes = vec![Vec::new(); vks.len()];
+        for leaf in leaves {
+            for (vk_notes, vk) in notes.iter_mut().zip(vks) {
+                if vk.owns(leaf.note.stealth_address()) {
+                    vk_notes.push((leaf.note.clone(), leaf.block_height));
+                }
+            }
+        }
+        notes
+    }
+}
+
+/// Single pass over the leaves for the notes of several view keys, from
+/// the lowest height their caches resume scanning from
+pub struct NoteScan<'a> {
+    vks: &'a [ViewKey],
+    caches: Vec<NoteCache>,
+    start_height: u64,
+    batch: Vec<NoteLeaf>,
+    found: Vec<Vec<EnrichedNote>>,
+}
+
+impl<'a> NoteScan<'a> {
+    /// Starts a scan for the notes of the view keys with the given caches
+    pub fn new(vks: &'a [ViewKey], caches: Vec<NoteCache>, start_block_height: u64) -> Self {
+        let start_height = caches
+            .iter()
+            .map(|vk_cache| vk_cache.resume_height(start_block_height))
+            .min()
+            .unwrap_or(start_block_height);
+        Self {
+            vks,
+            caches,
+            start_height,
+            batch: Vec::with_capacity(BATCH_LEAVES),
+            found: vec![Vec::new(); vks.len()],
+        }
+    }
+
+    /// Height leaves are to be scanned from
+    pub fn start_height(&self) -> u64 {
+        self.start_height
+    }
+
+    /// Tests a leaf against all view keys, once a batch of leaves is
+    /// complete
+    pub fn push(&mut self, leaf: &NoteLeaf) {
+        self.batch.push(leaf.clone());
+        if self.batch.len() == BATCH_LEAVES {
+            Scanner::find_owned(&self.batch, self.vks, &mut self.found);
+            self.batch.clear();
+        }
+    }
+
+    /// Records the notes found in the caches of the view keys, as scanned
+    /// up to the tip height
+    pub fn finish(mut self, tip_height: u64) -> Vec<NoteCache> {
+        Scanner::find_owned(&self.batch, self.vks, &mut self.found);
+        for (vk_cache, found) in self.caches.iter_mut().zip(self.found) {
+            vk_cache.update(self.start_height, tip_height, found);
+        }
+        self.caches
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use execution_core::transfer::phoenix::{Note, PublicKey, SecretKey};
+    use execution_core::JubJubScalar;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    /// Returns the view keys of `n` profiles and leaves owned in turn by
+    /// each of them and by a stranger, one every 5 blocks from height 100
+    fn keys_and_leaves(n: usize, count: u64) -> (Vec<ViewKey>, Vec<NoteLeaf>) {
+        let mut rng = StdRng::seed_from_u64(0xcafe);
+        let sks: Vec<_> = (0..=n).map(|_| SecretKey::random(&mut rng)).collect();
+        let pks: Vec<_> = sks.iter().map(PublicKey::from).collect();
+        let leaves = (0..count)
+            .map(|pos| {
+                let pk = &pks[pos as usize % pks.len()];
+                let blinder = [JubJubScalar::from(1u64), JubJubScalar::from(2u64)];
+                let mut note = Note::transparent(&mut rng, pk, pk, pos, blinder);
+                note.set_pos(pos);
+                NoteLeaf {
+                    block_height: 100 + pos * 5,
+                    note,
+                }
+            })
+            .collect();
+        (sks[..n].iter().map(ViewKey::from).collect(), leaves)
+    }
+
+    fn scan(
+        vks: &[ViewKey],
+        caches: Vec<NoteCache>,
+        start: u64,
+        leaves: &[NoteLeaf],
+        tip: u64,
+    ) -> Vec<NoteCache> {
+        let mut scan = NoteScan::new(vks, caches, start);
+        let start_height = scan.start_height();
+        for leaf in leaves
+            .iter()
+            .filter(|leaf| leaf.block_height >= start_height)
+        {
+            scan.push(leaf);
+        }
+        scan.finish(tip)
+    }
+
+    fn positions(vk_cache: &NoteCache) -> Vec<u64> {
+        vk_cache.notes.iter().map(|(note, _)| *note.pos()).collect()
+    }
+
+    #[test]
+    fn keys_scanned_together_or_alone_own_the_same_notes() {
+        let (vks, leaves) = keys_and_leaves(3, 40);
+        let mut found = vec![Vec::new(); vks.len()];
+        Scanner::find_owned(&leaves, &vks, &mut found);
+
+        for (i, vk) in vks.iter().enumerate() {
+            let mut alone = vec![Vec::new()];
+            Scanner::find_owned(&leaves, &[*vk], &mut alone);
+            let expected: Vec<u64> = (0..40).filter(|pos| pos % 4 == i as u64).collect();
+            let positions: Vec<u64> = alone[0].iter().map(|(note, _)| *note.pos()).collect();
+            assert_eq!(positions, expected);
+            let together: Vec<u64> = found[i].iter().map(|(note, _)| *note.pos()).collect();
+            assert_eq!(together, expected);
+        }
+
+        let caches = scan(&vks, vec![NoteCache::default(); 3], 100, &leaves, 300);
+        for (vk, vk_cache) in vks.iter().zip(&caches) {
+            let alone = scan(&[*vk], vec![NoteCache::default()], 100, &leaves, 300);
+            assert_eq!(positions(vk_cache), positions(&alone[0]));
+        }
+    }
+
+    #[test]
+    fn cache_heights_advance() {
+        let (vks, leaves) = keys_and_leaves(3, 40);
+        // the first key was scanned up to 150, the third from 120 to 180
+        let first = scan(
+            &vks[..1],
+            vec![NoteCache::default()],
+            100,
+            &leaves[..10],
+            150,
+        );
+        let mut third = NoteCache::default();
+        third.update(120, 180, vec![]);
+        assert_eq!((first[0].first_height, first[0].last_height), (100, 150));
+        let caches = vec![first[0].clone(), NoteCache::default(), third];
+
+        let mut scan_from = NoteScan::new(&vks, caches, 110);
+        // the third key restarts below the heights it was scanned for
+        assert_eq!(scan_from.start_height(), 110);
+        for leaf in leaves.iter().filter(|leaf| leaf.block_height >= 110) {
+            scan_from.push(leaf);
+        }
+        let caches = scan_from.finish(300);
+
+        let heights: Vec<_> = caches
+            .iter()
+            .map(|vk_cache| (vk_cache.first_height, vk_cache.last_height))
+            .collect();
+        assert_eq!(heights, [(100, 300), (110, 300), (110, 300)]);
+        let all: Vec<u64> = (0..40).filter(|pos| pos % 4 == 0).collect();
+        assert_eq!(positions(&caches[0]), all);
+        let above: Vec<u64> = (2..40).filter(|pos| pos % 4 == 1).collect();
+        assert_eq!(positions(&caches[1]), above);
+        let above: Vec<u64> = (2..40).filter(|pos| pos % 4 == 2).collect();
+        assert_eq!(positions(&caches[2]), above);
+
+        // once all keys are scanned up to the tip, scanning resumes from it
+        assert_eq!(NoteScan::new(&vks, caches, 110).start_height(), 300);
+    }
+}
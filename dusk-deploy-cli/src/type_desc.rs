@@ -0,0 +1,737 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use crate::Error;
+use execution_core::signatures::bls::PublicKey as BlsPublicKey;
+use execution_core::transfer::phoenix::PublicKey as PhoenixPublicKey;
+use rkyv::Archived;
+use std::fmt;
+use std::mem;
+use std::str::FromStr;
+
+/// Size of the archived representation of `Vec` and `String`
+/// (32 bit relative pointer or inline bytes, 32 bit length)
+const ARCHIVED_VEC_SIZE: usize = 8;
+const ARCHIVED_VEC_ALIGN: usize = 4;
+
+/// Largest archived type accepted, far beyond the size of any contract
+/// argument or result, keeping absurd descriptions from overflowing sizes
+/// or exhausting memory
+const MAX_SIZE: usize = 1 << 24;
+
+/// Description of a Rust type whose rkyv archived form is to be
+/// encoded or decoded, written in Rust-like syntax, e.g.:
+/// `Vec<(String, Option<[u8; 32]>)>` or `{active: bool @ 8, amount: u64 @ 0}`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TypeDesc {
+    U8,
+    U16,
+    U32,
+    U64,
+    U128,
+    I8,
+    I16,
+    I32,
+    I64,
+    I128,
+    Bool,
+    String,
+    BlsPublicKey,
+    PhoenixPublicKey,
+    Array(Box<TypeDesc>, usize),
+    Vec(Box<TypeDesc>),
+    Option(Box<TypeDesc>),
+    /// Tuple, unit type being an empty tuple
+    Tuple(Vec<TypeDesc>, Repr),
+    /// Struct with named fields
+    Struct {
+        fields: Vec<(String, TypeDesc)>,
+        repr: Repr,
+    },
+}
+
+/// Placement of the fields of an archived struct or tuple
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Repr {
+    /// Order chosen by rustc, only accepted when it cannot differ from the
+    /// declaration order
+    Rust,
+    /// Declaration order, for types archived with `repr(C)`
+    C,
+    /// Offsets of the fields in declaration order, e.g. as given by
+    /// `offset_of!` on the archived type
+    Offsets(Vec<usize>),
+}
+
+/// Memory layout of an archived type
+#[derive(Debug, Clone)]
+pub struct Layout {
+    pub size: usize,
+    pub align: usize,
+    /// Offsets of tuple or struct fields, in declaration order
+    pub offsets: Vec<usize>,
+}
+
+impl Layout {
+    fn scalar(size: usize, align: usize) -> Self {
+        Self {
+            size,
+            align,
+            offsets: vec![],
+        }
+    }
+}
+
+impl TypeDesc {
+    /// Returns the layout of the archived form of the type, as compiled
+    /// for wasm32, the target of contracts
+    pub fn layout(&self) -> Result<Layout, Error> {
+        self.checked_layout().ok_or_else(|| {
+            Error::InvalidTypeDescription(
+                format!("archived {} is larger than {} bytes", self, MAX_SIZE).into(),
+            )
+        })
+    }
+
+    /// Returns the layout of the archived form of the type, `None` if it
+    /// is larger than the maximum size
+    fn checked_layout(&self) -> Option<Layout> {
+        let layout = match self {
+            TypeDesc::U8 | TypeDesc::I8 | TypeDesc::Bool => Layout::scalar(1, 1),
+            TypeDesc::U16 | TypeDesc::I16 => Layout::scalar(2, 2),
+            TypeDesc::U32 | TypeDesc::I32 => Layout::scalar(4, 4),
+            TypeDesc::U64 | TypeDesc::I64 => Layout::scalar(8, 8),
+            // 128 bit integers are aligned to 8 bytes on wasm32, and on
+            // x86_64 before rustc 1.77
+            TypeDesc::U128 | TypeDesc::I128 => Layout::scalar(16, 8),
+            TypeDesc::String | TypeDesc::Vec(_) => {
+                Layout::scalar(ARCHIVED_VEC_SIZE, ARCHIVED_VEC_ALIGN)
+            }
+            // keys are made of 64 bit limbs, laid out alike on all targets
+            TypeDesc::BlsPublicKey => Layout::scalar(
+                mem::size_of::<Archived<BlsPublicKey>>(),
+                mem::align_of::<Archived<BlsPublicKey>>(),
+            ),
+            TypeDesc::PhoenixPublicKey => Layout::scalar(
+                mem::size_of::<Archived<PhoenixPublicKey>>(),
+                mem::align_of::<Archived<PhoenixPublicKey>>(),
+            ),
+            TypeDesc::Array(t, n) => {
+                let elem = t.checked_layout()?;
+                Layout::scalar(elem.size.checked_mul(*n)?, elem.align)
+            }
+            TypeDesc::Option(t) => {
+                // archived option is a `repr(u8)` enum
+                let inner = t.checked_layout()?;
+                let end = round_up(1, inner.align)?.checked_add(inner.size)?;
+                Layout::scalar(round_up(end, inner.align)?, inner.align)
+            }
+            TypeDesc::Tuple(types, repr) => {
+                let fields = types
+                    .iter()
+                    .map(TypeDesc::checked_layout)
+                    .collect::<Option<Vec<_>>>()?;
+                fields_layout(&fields, repr, true)?
+            }
+            TypeDesc::Struct { fields, repr } => {
+                let fields = fields
+                    .iter()
+                    .map(|(_, t)| t.checked_layout())
+                    .collect::<Option<Vec<_>>>()?;
+                fields_layout(&fields, repr, false)?
+            }
+        };
+        (layout.size <= MAX_SIZE).then_some(layout)
+    }
+
+    /// Offset of the value within an archived option
+    pub fn option_value_offset(inner: &TypeDesc) -> Result<usize, Error> {
+        // the tag is followed by the value, aligned
+        Ok(inner.layout()?.align)
+    }
+
+    /// Whether the archived form has invalid values, which rustc may use
+    /// when laying out an enclosing type. Keys are assumed to have some.
+    fn has_niche(&self) -> bool {
+        match self {
+            TypeDesc::Bool
+            | TypeDesc::Option(_)
+            | TypeDesc::BlsPublicKey
+            | TypeDesc::PhoenixPublicKey => true,
+            TypeDesc::Array(t, n) => *n > 0 && t.has_niche(),
+            TypeDesc::Tuple(types, _) => types.iter().any(TypeDesc::has_niche),
+            TypeDesc::Struct { fields, .. } => fields.iter().any(|(_, t)| t.has_niche()),
+            _ => false,
+        }
+    }
+}
+
+fn round_up(value: usize, align: usize) -> Option<usize> {
+    Some(value.checked_add(align - 1)? / align * align)
+}
+
+/// Number of leading fields rustc may reorder: all fields of a struct,
+/// all elements of a tuple but the last one, which may be unsized
+fn reorderable(len: usize, is_tuple: bool) -> usize {
+    if is_tuple {
+        len.saturating_sub(1)
+    } else {
+        len
+    }
+}
+
+/// Lays out struct or tuple fields
+fn fields_layout(fields: &[Layout], repr: &Repr, is_tuple: bool) -> Option<Layout> {
+    let align = fields.iter().map(|f| f.align).max().unwrap_or(1);
+    let offsets = match repr {
+        Repr::Offsets(offsets) => offsets.clone(),
+        Repr::C => sequential(fields, 0..fields.len())?,
+        Repr::Rust => {
+            // the fields rustc may reorder are in declaration order, zero
+            // sized ones excepted, which come first
+            let end = reorderable(fields.len(), is_tuple);
+            let zero_sized = (0..end).filter(|&i| fields[i].size == 0);
+            let sized = (0..end).filter(|&i| fields[i].size != 0);
+            sequential(fields, zero_sized.chain(sized).chain(end..fields.len()))?
+        }
+    };
+    let mut end = 0;
+    for (f, offset) in fields.iter().zip(&offsets) {
+        end = end.max(offset.checked_add(f.size)?);
+    }
+    Some(Layout {
+        size: round_up(end, align)?,
+        align,
+        offsets,
+    })
+}
+
+/// Offsets of fields placed one after the other in the given order
+fn sequential(fields: &[Layout], order: impl Iterator<Item = usize>) -> Option<Vec<usize>> {
+    let mut offsets = vec![0; fields.len()];
+    let mut offset = 0;
+    for i in order {
+        offset = round_up(offset, fields[i].align)?;
+        offsets[i] = offset;
+        offset = offset.checked_add(fields[i].size)?;
+    }
+    Some(offsets)
+}
+
+/// Whether the order rustc chooses for fields necessarily is the
+/// declaration order, whatever its reordering rules: at most one
+/// reorderable field is not zero sized, or all such fields have the same
+/// size and alignment and no niche
+fn rust_order_unambiguous(types: &[&TypeDesc], is_tuple: bool) -> Result<bool, Error> {
+    let mut sized = vec![];
+    for t in &types[..reorderable(types.len(), is_tuple)] {
+        let layout = t.layout()?;
+        if layout.size != 0 {
+            sized.push((*t, layout));
+        }
+    }
+    Ok(match sized.first() {
+        None => true,
+        Some(_) if sized.len() == 1 => true,
+        Some((_, first)) => sized.iter().all(|(t, layout)| {
+            layout.size == first.size && layout.align == first.align && !t.has_niche()
+        }),
+    })
+}
+
+impl FromStr for TypeDesc {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser { input: s, pos: 0 };
+        let desc = parser.parse_type()?;
+        parser.skip_whitespace();
+        if parser.pos != s.len() {
+            return Err(parser.error("unexpected trailing characters"));
+        }
+        // types too large to be laid out are rejected up front
+        desc.layout()?;
+        Ok(desc)
+    }
+}
+
+impl fmt::Display for TypeDesc {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TypeDesc::U8 => write!(f, "u8"),
+            TypeDesc::U16 => write!(f, "u16"),
+            TypeDesc::U32 => write!(f, "u32"),
+            TypeDesc::U64 => write!(f, "u64"),
+            TypeDesc::U128 => write!(f, "u128"),
+            TypeDesc::I8 => write!(f, "i8"),
+            TypeDesc::I16 => write!(f, "i16"),
+            TypeDesc::I32 => write!(f, "i32"),
+            TypeDesc::I64 => write!(f, "i64"),
+            TypeDesc::I128 => write!(f, "i128"),
+            TypeDesc::Bool => write!(f, "bool"),
+            TypeDesc::String => write!(f, "String"),
+            TypeDesc::BlsPublicKey => write!(f, "BlsPublicKey"),
+            TypeDesc::PhoenixPublicKey => write!(f, "PhoenixPublicKey"),
+            TypeDesc::Array(t, n) => write!(f, "[{}; {}]", t, n),
+            TypeDesc::Vec(t) => write!(f, "Vec<{}>", t),
+            TypeDesc::Option(t) => write!(f, "Option<{}>", t),
+            TypeDesc::Tuple(types, repr) => {
+                write_repr(f, repr)?;
+                write!(f, "(")?;
+                for (i, t) in types.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", t)?;
+                    write_offset(f, repr, i)?;
+                }
+                if types.len() == 1 {
+                    write!(f, ",")?;
+                }
+                write!(f, ")")
+            }
+            TypeDesc::Struct { fields, repr } => {
+                write_repr(f, repr)?;
+                write!(f, "{{")?;
+                for (i, (name, t)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}: {}", name, t)?;
+                    write_offset(f, repr, i)?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+fn write_repr(f: &mut fmt::Formatter<'_>, repr: &Repr) -> fmt::Result {
+    match repr {
+        Repr::C => write!(f, "repr(C) "),
+        _ => Ok(()),
+    }
+}
+
+fn write_offset(f: &mut fmt::Formatter<'_>, repr: &Repr, i: usize) -> fmt::Result {
+    match repr {
+        Repr::Offsets(offsets) => write!(f, " @ {}", offsets[i]),
+        _ => Ok(()),
+    }
+}
+
+/// Recursive descent parser of type descriptions
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn error(&self, msg: &str) -> Error {
+        Error::InvalidTypeDescription(
+            format!("{} at position {} of '{}'", msg, self.pos, self.input).into(),
+        )
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.input[self.pos..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.skip_whitespace();
+        self.rest().chars().next()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += c.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), Error> {
+        if self.eat(c) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected '{}'", c)))
+        }
+    }
+
+    fn ident(&mut self) -> Result<&'a str, Error> {
+        self.skip_whitespace();
+        let rest = self.rest();
+        let len = rest
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .unwrap_or(rest.len());
+        if len == 0 {
+            return Err(self.error("expected identifier"));
+        }
+        self.pos += len;
+        Ok(&rest[..len])
+    }
+
+    fn number(&mut self, what: &str) -> Result<usize, Error> {
+        let ident = self.ident()?;
+        ident
+            .replace('_', "")
+            .parse()
+            .map_err(|_| self.error(&format!("expected {}", what)))
+    }
+
+    /// Parses the offset of a field, given after `@`
+    fn offset(&mut self) -> Result<Option<usize>, Error> {
+        if self.eat('@') {
+            Ok(Some(self.number("field offset")?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn parse_type(&mut self) -> Result<TypeDesc, Error> {
+        match self.peek() {
+            Some('(') => self.parse_tuple(false),
+            Some('[') => {
+                self.pos += 1;
+                let t = self.parse_type()?;
+                self.expect(';')?;
+                let n = self.number("array length")?;
+                self.expect(']')?;
+                Ok(TypeDesc::Array(Box::new(t), n))
+            }
+            Some('{') => self.parse_struct(false),
+            _ => {
+                let ident = self.ident()?;
+                Ok(match ident {
+                    "u8" => TypeDesc::U8,
+                    "u16" => TypeDesc::U16,
+                    "u32" => TypeDesc::U32,
+                    "u64" => TypeDesc::U64,
+                    "u128" => TypeDesc::U128,
+                    "i8" => TypeDesc::I8,
+                    "i16" => TypeDesc::I16,
+                    "i32" => TypeDesc::I32,
+                    "i64" => TypeDesc::I64,
+                    "i128" => TypeDesc::I128,
+                    "bool" => TypeDesc::Bool,
+                    "String" => TypeDesc::String,
+                    "BlsPublicKey" => TypeDesc::BlsPublicKey,
+                    "PhoenixPublicKey" => TypeDesc::PhoenixPublicKey,
+                    "ContractId" => TypeDesc::Array(Box::new(TypeDesc::U8), 32),
+                    "Vec" => {
+                        self.expect('<')?;
+                        let t = self.parse_type()?;
+                        self.expect('>')?;
+                        TypeDesc::Vec(Box::new(t))
+                    }
+                    "Option" => {
+                        self.expect('<')?;
+                        let t = self.parse_type()?;
+                        self.expect('>')?;
+                        TypeDesc::Option(Box::new(t))
+                    }
+                    "repr" => {
+                        self.expect('(')?;
+                        if self.ident()? != "C" {
+                            return Err(self.error("only repr(C) is supported"));
+                        }
+                        self.expect(')')?;
+                        return match self.peek() {
+                            Some('(') => self.parse_tuple(true),
+                            _ => self.parse_struct(true),
+                        };
+                    }
+                    _ => return Err(self.error(&format!("unknown type '{}'", ident))),
+                })
+            }
+        }
+    }
+
+    fn parse_tuple(&mut self, repr_c: bool) -> Result<TypeDesc, Error> {
+        let start = self.pos;
+        self.expect('(')?;
+        let mut types = vec![];
+        let mut offsets = vec![];
+        while !self.eat(')') {
+            types.push(self.parse_type()?);
+            offsets.push(self.offset()?);
+            if !self.eat(',') {
+                self.expect(')')?;
+                // `(T)` is not a tuple
+                if types.len() == 1 && !repr_c && offsets[0].is_none() {
+                    return Ok(types.remove(0));
+                }
+                break;
+            }
+        }
+        let refs: Vec<_> = types.iter().collect();
+        let repr = self.repr(start, repr_c, offsets, &refs, true)?;
+        Ok(TypeDesc::Tuple(types, repr))
+    }
+
+    fn parse_struct(&mut self, repr_c: bool) -> Result<TypeDesc, Error> {
+        let start = self.pos;
+        self.expect('{')?;
+        let mut fields = vec![];
+        let mut offsets = vec![];
+        while !self.eat('}') {
+            let name = self.ident()?.to_string();
+            self.expect(':')?;
+            fields.push((name, self.parse_type()?));
+            offsets.push(self.offset()?);
+            if !self.eat(',') {
+                self.expect('}')?;
+                break;
+            }
+        }
+        let types: Vec<_> = fields.iter().map(|(_, t)| t).collect();
+        let repr = self.repr(start, repr_c, offsets, &types, false)?;
+        Ok(TypeDesc::Struct { fields, repr })
+    }
+
+    /// Determines the placement of the fields of a struct or tuple starting
+    /// at `start`: offsets must be given for all fields or none, and
+    /// fields laid out by rustc must be in an unambiguous order
+    fn repr(
+        &mut self,
+        start: usize,
+        repr_c: bool,
+        offsets: Vec<Option<usize>>,
+        types: &[&TypeDesc],
+        is_tuple: bool,
+    ) -> Result<Repr, Error> {
+        let end = self.pos;
+        self.pos = start;
+        let repr = if offsets.iter().all(Option::is_none) {
+            if repr_c {
+                Repr::C
+            } else if rust_order_unambiguous(types, is_tuple)? {
+                Repr::Rust
+            } else {
+                return Err(self.error(
+                    "field order chosen by rustc is ambiguous, \
+                     use repr(C) or give field offsets with `@`",
+                ));
+            }
+        } else {
+            if repr_c {
+                return Err(self.error("field offsets cannot be given with repr(C)"));
+            }
+            let offsets: Vec<usize> = offsets
+                .into_iter()
+                .collect::<Option<_>>()
+                .ok_or_else(|| self.error("field offsets must be given for all fields"))?;
+            self.check_offsets(&offsets, types)?;
+            Repr::Offsets(offsets)
+        };
+        self.pos = end;
+        Ok(repr)
+    }
+
+    /// Checks that explicit field offsets are aligned and that the fields do
+    /// not overlap
+    fn check_offsets(&self, offsets: &[usize], types: &[&TypeDesc]) -> Result<(), Error> {
+        let mut fields: Vec<(usize, Layout)> = offsets
+            .iter()
+            .zip(types)
+            .map(|(offset, t)| Ok((*offset, t.layout()?)))
+            .collect::<Result<_, Error>>()?;
+        for (offset, layout) in fields.iter() {
+            if offset % layout.align != 0 {
+                return Err(self.error(&format!(
+                    "field offset {} is not aligned to {}",
+                    offset, layout.align
+                )));
+            }
+        }
+        fields.retain(|(_, layout)| layout.size != 0);
+        fields.sort_by_key(|(offset, _)| *offset);
+        for pair in fields.windows(2) {
+            let ((offset, layout), (next, _)) = (&pair[0], &pair[1]);
+            if !matches!(offset.checked_add(layout.size), Some(end) if end <= *next) {
+                return Err(self.error(&format!(
+                    "field at offset {} overlaps the field at offset {}",
+                    offset, next
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> TypeDesc {
+        s.parse().unwrap_or_else(|e| panic!("{}: {}", s, e))
+    }
+
+    #[test]
+    fn display_round_trip() {
+        for s in [
+            "u8",
+            "i128",
+            "String",
+            "BlsPublicKey",
+            "[u16; 3]",
+            "Vec<Option<bool>>",
+            "()",
+            "(u8,)",
+            "(u8, String)",
+            "{a: String, b: Vec<u8>}",
+            "repr(C) {a: u8, b: u64, c: bool}",
+            "repr(C) (u8, u64, bool)",
+            "{a: u8 @ 8, b: u64 @ 0, c: bool @ 9}",
+            "(u8 @ 8, u64 @ 0, bool @ 9)",
+        ] {
+            assert_eq!(parse(s).to_string(), s);
+        }
+        assert_eq!(parse("ContractId"), parse("[u8; 32]"));
+        assert_eq!(parse("(u32)"), TypeDesc::U32);
+        assert_eq!(parse(" { a : u8 @ 1_0 , } "), parse("{a: u8 @ 10}"));
+    }
+
+    #[test]
+    fn syntax_errors() {
+        for s in [
+            "",
+            "u7",
+            "Vec<u8",
+            "[u8]",
+            "[u8; n]",
+            "{a u8}",
+            "repr(packed) {a: u8}",
+            "u8 u8",
+            "{a: u8 @ x}",
+        ] {
+            assert!(s.parse::<TypeDesc>().is_err(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn ambiguous_rust_layouts_are_rejected() {
+        for s in [
+            // tuples never reorder their last element
+            "(u8, u64)",
+            "(u32, u32, u64)",
+            "(u64, (), String)",
+            // at most one field not zero sized
+            "{a: u64}",
+            "{a: (), b: bool, c: [u64; 0]}",
+            // fields of the same size and alignment without niche
+            "{a: u32, b: i32}",
+            "{a: String, b: Vec<bool>}",
+            "{a: (u8, u8), b: [u8; 2]}",
+        ] {
+            assert!(s.parse::<TypeDesc>().is_ok(), "{}", s);
+        }
+        for s in [
+            "(u8, u64, u8)",
+            "(bool, u64, u32)",
+            "(u64, (), u8, String)",
+            "{a: u8, b: u64}",
+            "{a: u32, b: [u8; 4]}",
+            "{a: bool, b: bool}",
+            "{a: Option<u8>, b: u16}",
+            "{a: u32, b: {c: bool, d: [u8; 3]}}",
+            "Vec<{a: BlsPublicKey, b: BlsPublicKey}>",
+        ] {
+            assert!(s.parse::<TypeDesc>().is_err(), "{}", s);
+        }
+        assert!("repr(C) {a: u8, b: u64}".parse::<TypeDesc>().is_ok());
+    }
+
+    #[test]
+    fn invalid_offsets_are_rejected() {
+        for s in [
+            "{a: u8 @ 8, b: u64}",
+            "repr(C) {a: u8 @ 0}",
+            "{a: u8 @ 0, b: u64 @ 4}",
+            "{a: u64 @ 0, b: u8 @ 7}",
+            "(u16 @ 0, u16 @ 1)",
+            "(u32 @ 0, u16 @ 2)",
+        ] {
+            assert!(s.parse::<TypeDesc>().is_err(), "{}", s);
+        }
+        assert!("{a: u8 @ 0, b: () @ 0}".parse::<TypeDesc>().is_ok());
+    }
+
+    #[test]
+    fn oversized_types_are_rejected() {
+        for s in [
+            "[u64; 18446744073709551615]",
+            "[[u8; 4294967296]; 4294967296]",
+            "Option<[u8; 18446744073709551615]>",
+            "repr(C) (u8, [u64; 2305843009213693951])",
+            "{a: u8 @ 18446744073709551615}",
+            "[u8; 16777217]",
+        ] {
+            assert!(s.parse::<TypeDesc>().is_err(), "{}", s);
+        }
+        assert!("[u8; 16777216]".parse::<TypeDesc>().is_ok());
+
+        let desc = TypeDesc::Array(Box::new(TypeDesc::U64), usize::MAX);
+        assert!(desc.layout().is_err());
+        let desc = TypeDesc::Tuple(vec![desc], Repr::C);
+        assert!(desc.layout().is_err());
+    }
+
+    #[test]
+    fn layouts() {
+        let layout = parse("(u8, u64)").layout().unwrap();
+        assert_eq!(
+            (layout.size, layout.align, layout.offsets),
+            (16, 8, vec![0, 8])
+        );
+
+        let layout = parse("repr(C) {a: u8, b: u64, c: bool}").layout().unwrap();
+        assert_eq!((layout.size, layout.align), (24, 8));
+        assert_eq!(layout.offsets, [0, 8, 16]);
+
+        let layout = parse("{a: u8 @ 8, b: u64 @ 0, c: bool @ 9}")
+            .layout()
+            .unwrap();
+        assert_eq!((layout.size, layout.align), (16, 8));
+        assert_eq!(layout.offsets, [8, 0, 9]);
+
+        let layout = parse("{a: (), b: u16, c: [u64; 0]}").layout().unwrap();
+        assert_eq!((layout.size, layout.align), (8, 8));
+        assert_eq!(layout.offsets, [0, 0, 0]);
+
+        let layout = parse("Option<u32>").layout().unwrap();
+        assert_eq!((layout.size, layout.align), (8, 4));
+        assert_eq!(TypeDesc::option_value_offset(&TypeDesc::U32).unwrap(), 4);
+
+        let layout = parse("u128").layout().unwrap();
+        assert_eq!((layout.size, layout.align), (16, 8));
+
+        let layout = parse("()").layout().unwrap();
+        assert_eq!((layout.size, layout.align), (0, 1));
+    }
+
+    #[test]
+    fn layouts_match_archived_types() {
+        fn check<T: rkyv::Archive>(desc: &str) {
+            let layout = parse(desc).layout().unwrap();
+            assert_eq!(layout.size, mem::size_of::<Archived<T>>(), "{}", desc);
+            assert_eq!(layout.align, mem::align_of::<Archived<T>>(), "{}", desc);
+        }
+        check::<(u8, u64)>("(u8, u64)");
+        check::<(u32, u32, u16)>("(u32, u32, u16)");
+        check::<(String, Vec<u8>, bool)>("(String, Vec<u8>, bool)");
+        check::<Option<u64>>("Option<u64>");
+        check::<Option<(u8, u32)>>("Option<(u8, u32)>");
+        check::<[Option<u16>; 3]>("[Option<u16>; 3]");
+        check::<()>("()");
+    }
+}
@@ -0,0 +1,222 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use crate::gen_id::ContractIdGenerator;
+use crate::Error;
+use parking_lot::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::thread;
+
+/// Number of hexadecimal digits of a contract id
+const ID_NIBBLES: usize = 64;
+
+/// Wildcard digit of a mask
+const WILDCARD: char = '_';
+
+/// Hexadecimal digits a contract id must have at given positions
+#[derive(Debug, Clone, Default)]
+pub struct Pattern {
+    nibbles: Vec<(usize, u8)>,
+}
+
+impl Pattern {
+    /// Builds a pattern from digits the id must start with, digits it must
+    /// end with, and a mask of digits from its start, where `_` matches any
+    /// digit
+    pub fn new(
+        prefix: Option<&str>,
+        suffix: Option<&str>,
+        mask: Option<&str>,
+    ) -> Result<Self, Error> {
+        let mut pattern = Pattern::default();
+        if let Some(prefix) = prefix {
+            pattern.add(0, prefix, false)?;
+        }
+        if let Some(suffix) = suffix {
+            let start = ID_NIBBLES
+                .checked_sub(suffix.len())
+                .ok_or(Error::InvalidArgument("suffix is too long".into()))?;
+            pattern.add(start, suffix, false)?;
+        }
+        if let Some(mask) = mask {
+            pattern.add(0, mask, true)?;
+        }
+        if pattern.nibbles.is_empty() {
+            return Err(Error::InvalidArgument(
+                "a prefix, a suffix or a mask is required".into(),
+            ));
+        }
+        Ok(pattern)
+    }
+
+    fn add(&mut self, start: usize, digits: &str, wildcards: bool) -> Result<(), Error> {
+        if start + digits.len() > ID_NIBBLES {
+            return Err(Error::InvalidArgument(
+                format!("pattern `{}` is longer than a contract id", digits).into(),
+            ));
+        }
+        for (i, c) in digits.chars().enumerate() {
+            if wildcards && c == WILDCARD {
+                continue;
+            }
+            let nibble = c.to_digit(16).ok_or(Error::InvalidArgument(
+                format!("invalid hexadecimal digit `{}` in `{}`", c, digits).into(),
+            ))?;
+            self.nibbles.push((start + i, nibble as u8));
+        }
+        Ok(())
+    }
+
+    pub fn matches(&self, id: &[u8; 32]) -> bool {
+        self.nibbles.iter().all(|&(pos, nibble)| {
+            let byte = id[pos / 2];
+            let digit = if pos % 2 == 0 { byte >> 4 } else { byte & 0x0f };
+            digit == nibble
+        })
+    }
+
+    /// Expected number of nonces tried per match
+    pub fn difficulty(&self) -> f64 {
+        16f64.powi(self.nibbles.len() as i32)
+    }
+}
+
+/// Outcome of a search, matches ordered by nonce
+#[derive(Debug, Clone)]
+pub struct Search {
+    pub matches: Vec<(u64, [u8; 32])>,
+    pub iterations: u64,
+}
+
+pub struct Vanity;
+
+impl Vanity {
+    /// Searches nonces from `start_nonce` on, on `threads` threads, until
+    /// `count` matching contract ids are found or `max_iterations` nonces
+    /// are tried
+    pub fn search(
+        generator: &ContractIdGenerator,
+        pattern: &Pattern,
+        start_nonce: u64,
+        count: usize,
+        max_iterations: Option<u64>,
+        threads: usize,
+    ) -> Search {
+        let threads = threads.max(1) as u64;
+        // nonces past `u64::MAX` are never tried
+        let max_iterations = max_iterations
+            .unwrap_or(u64::MAX)
+            .min(u64::MAX - start_nonce);
+        let matches = Mutex::new(Vec::new());
+        let iterations = AtomicU64::new(0);
+        let done = AtomicBool::new(false);
+
+        thread::scope(|s| {
+            for t in 0..threads {
+                let (matches, iterations, done) = (&matches, &iterations, &done);
+                s.spawn(move || {
+                    let mut i = t;
+                    let mut tried = 0;
+                    while i < max_iterations && !done.load(Ordering::Relaxed) {
+                        let nonce = start_nonce + i;
+                        let id = generator.gen(nonce);
+                        if pattern.matches(&id) {
+                            let mut matches = matches.lock();
+                            matches.push((nonce, id));
+                            if matches.len() >= count {
+                                done.store(true, Ordering::Relaxed);
+                            }
+                        }
+                        tried += 1;
+                        i = match i.checked_add(threads) {
+                            Some(i) => i,
+                            None => break,
+                        };
+                    }
+                    iterations.fetch_add(tried, Ordering::Relaxed);
+                });
+            }
+        });
+
+        let mut matches = matches.into_inner();
+        matches.sort_unstable_by_key(|(nonce, _)| *nonce);
+        matches.truncate(count);
+        Search {
+            matches,
+            iterations: iterations.into_inner(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn id_from_hex(hex: &str) -> [u8; 32] {
+        hex::decode(hex).unwrap().try_into().unwrap()
+    }
+
+    const ID: &str = "0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef";
+
+    #[test]
+    fn prefix_suffix_and_mask() {
+        let id = id_from_hex(ID);
+        let matching = [
+            (Some("0123"), None, None),
+            (None, Some("cdef"), None),
+            (Some("01"), Some("ef"), None),
+            (None, None, Some("0_2_4")),
+            (Some("0"), Some("f"), Some("_1")),
+            (Some("0123456789ABCDEF"), None, None),
+            (Some(ID), None, None),
+        ];
+        for (prefix, suffix, mask) in matching {
+            let pattern = Pattern::new(prefix, suffix, mask).unwrap();
+            assert!(pattern.matches(&id), "{:?} {:?} {:?}", prefix, suffix, mask);
+        }
+        let mismatching = [
+            (Some("1"), None, None),
+            (None, Some("cdee"), None),
+            (Some("0123"), Some("0"), None),
+            (None, None, Some("_0")),
+        ];
+        for (prefix, suffix, mask) in mismatching {
+            let pattern = Pattern::new(prefix, suffix, mask).unwrap();
+            assert!(
+                !pattern.matches(&id),
+                "{:?} {:?} {:?}",
+                prefix,
+                suffix,
+                mask
+            );
+        }
+    }
+
+    #[test]
+    fn difficulty_counts_fixed_digits() {
+        let pattern = Pattern::new(Some("ab"), Some("c"), Some("__d")).unwrap();
+        assert_eq!(pattern.difficulty(), 16f64.powi(4));
+    }
+
+    #[test]
+    fn invalid_patterns() {
+        assert!(Pattern::new(None, None, None).is_err());
+        assert!(Pattern::new(Some("0g"), None, None).is_err());
+        assert!(Pattern::new(Some("_"), None, None).is_err());
+        assert!(Pattern::new(None, Some(&"0".repeat(65)), None).is_err());
+        assert!(Pattern::new(None, None, Some(&"_".repeat(65))).is_err());
+    }
+
+    #[test]
+    fn search_near_the_last_nonce() {
+        let generator = ContractIdGenerator::new([0u8; 4], [1u8; 4]);
+        let id = generator.gen(u64::MAX - 1);
+        let pattern = Pattern::new(Some(&hex::encode(id)), None, None).unwrap();
+        let search = Vanity::search(&generator, &pattern, u64::MAX - 3, 10, Some(10), 2);
+        assert_eq!(search.matches, vec![(u64::MAX - 1, id)]);
+        assert_eq!(search.iterations, 3);
+    }
+}
@@ -0,0 +1,51 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use crate::block::Block;
+use crate::output::Verification;
+use crate::Error;
+use rusk_http_client::{ContractId, ContractInquirer, RuskHttpClient};
+
+pub struct Verifier;
+
+impl Verifier {
+    /// Verifies a deployment included in a block: the contract must exist
+    /// and the smoke test method, if given, is queried without arguments
+    /// and must not fail.
+    ///
+    /// The bytecode stored for the contract is not compared with the
+    /// deployed bytecode, the node exposing no endpoint returning it.
+    pub fn verify(
+        client: &RuskHttpClient,
+        contract_id: ContractId,
+        smoke_test: Option<&str>,
+    ) -> Result<Verification, Error> {
+        let mut verification = Verification {
+            smoke_test: smoke_test.map(String::from),
+            ..Verification::default()
+        };
+
+        verification.contract_exists =
+            ContractInquirer::contract_exists(client, contract_id).wait()?;
+        if !verification.contract_exists {
+            verification.error = Some(format!(
+                "contract {} does not exist",
+                hex::encode(contract_id)
+            ));
+            return Ok(verification);
+        }
+
+        if let Some(method) = smoke_test {
+            if let Err(err) =
+                ContractInquirer::query_contract_raw(client, vec![], contract_id, method).wait()
+            {
+                verification.error = Some(format!("smoke test {} failed: {}", method, err));
+            }
+        }
+
+        Ok(verification)
+    }
+}
@@ -4,29 +4,68 @@
 //
 // Copyright (c) DUSK NETWORK. All rights reserved.
 
-use crate::dcli_prover_client::DCliProverClient;
+use crate::dcli_prover_client::{DCliProverClient, Propagation};
 use crate::dcli_state_client::DCliStateClient;
 use crate::dcli_store::DCliStore;
+use crate::offline::OfflineState;
+use crate::polling::WaitPolicy;
+use crate::progress::ProgressCallback;
 use crate::Error;
+use execution_core::transfer::phoenix::ViewKey;
 use rusk_http_client::RuskHttpClient;
+use std::path::PathBuf;
 use wallet::Wallet;
 
 pub struct WalletBuilder;
 
 impl WalletBuilder {
+    #[allow(clippy::too_many_arguments)]
     pub fn build(
         url_state: impl AsRef<str>,
         url_prover: impl AsRef<str>,
-        seed: &[u8; 64],
+        store: DCliStore,
         start_block_height: u64,
+        cache_dir: Option<PathBuf>,
+        view_keys: Vec<ViewKey>,
+        progress: Option<ProgressCallback>,
+        propagation: Propagation,
+        wait: WaitPolicy,
     ) -> Result<Wallet<DCliStore, DCliStateClient, DCliProverClient>, Error> {
         let state_client = RuskHttpClient::new(url_state.as_ref().to_string());
         let prover_client = RuskHttpClient::new(url_prover.as_ref().to_string());
 
         Ok(wallet::Wallet::new(
-            DCliStore::new(seed),
-            DCliStateClient::new(state_client.clone(), start_block_height),
-            DCliProverClient::new(state_client.clone(), prover_client.clone()),
+            store,
+            DCliStateClient::new(state_client.clone(), start_block_height)
+                .with_cache_dir(cache_dir)
+                .with_view_keys(view_keys)
+                .with_progress(progress),
+            DCliProverClient::new(
+                state_client.clone(),
+                prover_client.clone(),
+                propagation,
+                wait,
+            ),
+        ))
+    }
+
+    /// Builds a wallet using an exported state, which signs transactions
+    /// without proving or propagating them
+    pub fn build_offline(
+        store: DCliStore,
+        state: OfflineState,
+    ) -> Result<Wallet<DCliStore, DCliStateClient, DCliProverClient>, Error> {
+        let client = RuskHttpClient::new(String::new());
+
+        Ok(wallet::Wallet::new(
+            store,
+            DCliStateClient::offline(state),
+            DCliProverClient::new(
+                client.clone(),
+                client,
+                Propagation::Offline,
+                WaitPolicy::default(),
+            ),
         ))
     }
 }
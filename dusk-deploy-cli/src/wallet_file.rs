@@ -0,0 +1,212 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use crate::Error;
+use aes::cipher::block_padding::Pkcs7;
+use aes::cipher::{BlockDecryptMut, KeyIvInit};
+use sha2::{Digest, Sha256};
+use std::path::Path;
+
+type Aes256CbcDec = cbc::Decryptor<aes::Aes256>;
+
+/// Magic number of rusk wallet files, on 3 bytes
+const MAGIC: [u8; 3] = [0x72, 0x73, 0x6b];
+const FILE_TYPE: [u8; 2] = [0x02, 0x00];
+const RESERVED: [u8; 2] = [0x00, 0x00];
+/// Magic number, file type, reserved bytes and 5 bytes of version
+const HEADER_SIZE: usize = 12;
+/// Magic number and 2 bytes of version of the old wallet CLI
+const OLD_HEADER_SIZE: usize = 5;
+/// Version of some legacy files, ending with 2 zero bytes
+const LEGACY_HEADER_SIZE: usize = 3;
+const BLOCK_SIZE: usize = 16;
+const IV_SIZE: usize = 16;
+const SEED_SIZE: usize = 64;
+
+/// Layout of a rusk wallet `.dat` file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    /// Encrypted seed, without header
+    Legacy,
+    /// Encrypted seed, after 3 bytes of version
+    LegacyVersioned,
+    /// Header of the old wallet CLI, with major and minor version
+    OldWalletCli(u8, u8),
+    /// Header of the rusk binary file format
+    RuskBinary,
+}
+
+impl Format {
+    fn header_size(self) -> usize {
+        match self {
+            Format::Legacy => 0,
+            Format::LegacyVersioned => LEGACY_HEADER_SIZE,
+            Format::OldWalletCli(..) => OLD_HEADER_SIZE,
+            Format::RuskBinary => HEADER_SIZE,
+        }
+    }
+
+    /// Key decrypting the seed. The binary format hashes the password with
+    /// blake3, older formats with SHA-256.
+    fn key(self, password: &str) -> [u8; 32] {
+        match self {
+            Format::RuskBinary => blake3::hash(password.as_bytes()).into(),
+            _ => Sha256::digest(password.as_bytes()).into(),
+        }
+    }
+}
+
+/// Wallet file of the rusk wallet, holding a seed encrypted with AES-256-CBC
+/// under a hash of the password
+#[derive(Debug, Clone)]
+pub struct WalletFile {
+    bytes: Vec<u8>,
+}
+
+impl WalletFile {
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        Ok(Self {
+            bytes: std::fs::read(path)?,
+        })
+    }
+
+    /// The encrypted seed is made of whole AES blocks, and headers differ in
+    /// size modulo the block size, so the size of the file tells which
+    /// header it must start with
+    fn format(&self) -> Result<Format, Error> {
+        let bytes = &self.bytes;
+        let format = match bytes.len() % BLOCK_SIZE {
+            0 => Format::Legacy,
+            LEGACY_HEADER_SIZE if bytes[1..3] == [0, 0] => Format::LegacyVersioned,
+            OLD_HEADER_SIZE if bytes[..3] == MAGIC => Format::OldWalletCli(bytes[3], bytes[4]),
+            HEADER_SIZE
+                if bytes[..3] == MAGIC && bytes[3..5] == FILE_TYPE && bytes[5..7] == RESERVED =>
+            {
+                Format::RuskBinary
+            }
+            _ => return Err(Error::Keystore("unrecognised wallet file".into())),
+        };
+        Ok(format)
+    }
+
+    /// Decrypts the seed with the password
+    pub fn seed(&self, password: &str) -> Result<[u8; 64], Error> {
+        let format = self.format()?;
+        match format {
+            Format::OldWalletCli(1, 0) | Format::OldWalletCli(2, 0) => {}
+            Format::OldWalletCli(major, minor) => {
+                return Err(Error::Keystore(
+                    format!("unsupported wallet file version {}.{}", major, minor).into(),
+                ))
+            }
+            _ => {}
+        }
+
+        let content = decrypt(&self.bytes[format.header_size()..], &format.key(password))?;
+        // the seed may be followed by the number of addresses
+        content
+            .get(..SEED_SIZE)
+            .and_then(|seed| seed.try_into().ok())
+            .ok_or(Error::Keystore("corrupted wallet file".into()))
+    }
+}
+
+fn decrypt(data: &[u8], key: &[u8; 32]) -> Result<Vec<u8>, Error> {
+    if data.len() < IV_SIZE {
+        return Err(Error::Keystore("corrupted wallet file".into()));
+    }
+    let (iv, encrypted) = data.split_at(IV_SIZE);
+    Aes256CbcDec::new_from_slices(key, iv)
+        .map_err(|_| Error::Keystore("invalid key".into()))?
+        .decrypt_padded_vec_mut::<Pkcs7>(encrypted)
+        .map_err(|_| Error::Keystore("wrong password or corrupted wallet file".into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Seed of the recovery phrase made of eleven "abandon" and "about"
+    const SEED: &str = "5eb00bbddcf069084889a8ab9155568165f5c453ccb85e70811aaed6f6da5fc1\
+                        9a5ac40b389cd370d086206dec8aa6c43daea6690f20ad3d8d48b2d2ce9e38e4";
+    const PASSWORD: &str = "password";
+
+    /// Wallet file of each format, holding the seed encrypted under the
+    /// password
+    fn fixture(name: &str) -> WalletFile {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../test/wallets")
+            .join(name);
+        WalletFile::load(&path).unwrap()
+    }
+
+    #[test]
+    fn formats() {
+        let format = |name| fixture(name).format().unwrap();
+        assert_eq!(format("legacy.dat"), Format::Legacy);
+        assert_eq!(format("legacy_versioned.dat"), Format::LegacyVersioned);
+        assert_eq!(format("wallet_cli_v1.dat"), Format::OldWalletCli(1, 0));
+        assert_eq!(format("wallet_cli_v2.dat"), Format::OldWalletCli(2, 0));
+        assert_eq!(format("rusk_binary.dat"), Format::RuskBinary);
+
+        // ciphertexts starting like headers are not mistaken for them
+        let mut legacy = fixture("legacy.dat");
+        legacy.bytes[..7].copy_from_slice(&[0x72, 0x73, 0x6b, 2, 0, 0, 0]);
+        assert_eq!(legacy.format().unwrap(), Format::Legacy);
+        let mut old = fixture("wallet_cli_v2.dat");
+        old.bytes[5..7].copy_from_slice(&RESERVED);
+        assert_eq!(old.format().unwrap(), Format::OldWalletCli(2, 0));
+
+        for len in [1, 2, 95, 100, 107] {
+            let mut file = fixture("rusk_binary.dat");
+            file.bytes.truncate(len);
+            assert!(file.format().is_err());
+        }
+    }
+
+    #[test]
+    fn password_hashes() {
+        assert_eq!(
+            hex::encode(Format::RuskBinary.key(PASSWORD)),
+            "7f2611ba158b6dcea4a69c229c303358c5e04493abeadee106a4bfa464d55787"
+        );
+        for format in [
+            Format::Legacy,
+            Format::LegacyVersioned,
+            Format::OldWalletCli(1, 0),
+        ] {
+            assert_eq!(
+                hex::encode(format.key(PASSWORD)),
+                "5e884898da28047151d0e56f8dc6292773603d0d6aabbdd62a11ef721d1542d8"
+            );
+        }
+    }
+
+    #[test]
+    fn seeds() {
+        for name in [
+            "legacy.dat",
+            "legacy_versioned.dat",
+            "wallet_cli_v1.dat",
+            "wallet_cli_v2.dat",
+            "rusk_binary.dat",
+        ] {
+            let file = fixture(name);
+            assert_eq!(hex::encode(file.seed(PASSWORD).unwrap()), SEED, "{}", name);
+            assert!(file.seed("wrong").is_err(), "{}", name);
+        }
+
+        for len in [HEADER_SIZE, HEADER_SIZE + IV_SIZE] {
+            let mut truncated = fixture("rusk_binary.dat");
+            truncated.bytes.truncate(len);
+            assert!(truncated.seed(PASSWORD).is_err());
+        }
+
+        let mut unsupported = fixture("wallet_cli_v2.dat");
+        unsupported.bytes[3] = 3;
+        assert!(unsupported.seed(PASSWORD).is_err());
+    }
+}
@@ -9,12 +9,34 @@ pub const MAX_RESPONSE_SIZE: usize = 65536;
 
 #[derive(Debug, Default, serde::Deserialize, serde::Serialize)]
 pub struct SpentTx {
+    #[serde(default)]
     pub id: String,
     #[serde(default)]
     pub raw: String,
+    #[serde(alias = "error", default)]
     pub err: Option<String>,
     #[serde(alias = "gasSpent", default)]
     pub gas_spent: f64,
+    #[serde(alias = "blockHeight", default)]
+    pub block_height: u64,
+    #[serde(alias = "blockHash", default)]
+    pub block_hash: String,
+    #[serde(default)]
+    pub tx: Option<TxDetails>,
+}
+
+#[derive(Debug, Default, serde::Deserialize, serde::Serialize)]
+pub struct TxDetails {
+    #[serde(alias = "gasLimit", default)]
+    pub gas_limit: u64,
+    #[serde(alias = "gasPrice", default)]
+    pub gas_price: u64,
+}
+
+#[derive(Debug, Default, serde::Deserialize, serde::Serialize)]
+pub struct BlockEventsResponse {
+    #[serde(alias = "blockEvents", default)]
+    pub block_events: serde_json::Value,
 }
 
 #[derive(Debug, Default, serde::Deserialize, serde::Serialize)]
@@ -36,3 +58,9 @@ pub struct Block {
 pub struct QueryResult {
     pub block: Block,
 }
+
+#[derive(Debug, Default, serde::Deserialize, serde::Serialize)]
+pub struct CheckBlockResponse {
+    #[serde(alias = "checkBlock", default)]
+    pub check_block: bool,
+}
@@ -4,7 +4,11 @@
 //
 // Copyright (c) DUSK NETWORK. All rights reserved.
 
-use crate::{Error, QueryResult, RuskHttpClient, RuskRequest, SpentTxResponse};
+use crate::{
+    BlockEventsResponse, CheckBlockResponse, Error, QueryResult, RuskHttpClient, RuskRequest,
+    SpentTx, SpentTxResponse,
+};
+use serde_json::Value;
 use std::borrow::Cow;
 
 pub struct BlockchainInquirer;
@@ -26,6 +30,60 @@ impl BlockchainInquirer {
         }
     }
 
+    pub async fn retrieve_spent_tx<S>(txid: S, client: &RuskHttpClient) -> Result<SpentTx, Error>
+    where
+        S: AsRef<str>,
+    {
+        let query = "query { tx(hash: \"####\") { id, err, gasSpent, blockHeight, blockHash, \
+                     tx { gasLimit, gasPrice } }}"
+            .replace("####", txid.as_ref());
+        let response = Self::gql_query(client, query.as_str()).await?;
+        let result = serde_json::from_slice::<SpentTxResponse>(&response)?;
+        result
+            .tx
+            .ok_or(Error::NotFound(Cow::from(txid.as_ref().to_string())))
+    }
+
+    /// Simulates the execution of a serialized transaction, without
+    /// including it in a block
+    pub async fn simulate_tx(tx_bytes: Vec<u8>, client: &RuskHttpClient) -> Result<SpentTx, Error> {
+        let request = RuskRequest::new("simulate_tx", tx_bytes);
+        let response = client.call(2, "Chain", &request).await?;
+        Ok(serde_json::from_slice::<SpentTx>(&response)?)
+    }
+
+    /// Retrieves the contract events emitted in a block, as JSON values
+    pub async fn block_events(
+        block_height: u64,
+        client: &RuskHttpClient,
+    ) -> Result<Vec<Value>, Error> {
+        let query = format!("query {{ blockEvents(height: {}) }}", block_height);
+        let response = Self::gql_query(client, query).await?;
+        let result = serde_json::from_slice::<BlockEventsResponse>(&response)?;
+        // events may be returned as a JSON string
+        Ok(match result.block_events {
+            Value::Array(events) => events,
+            Value::String(s) => serde_json::from_str(&s)?,
+            _ => vec![],
+        })
+    }
+
+    /// Checks whether the block of the given height and hash is final
+    pub async fn is_finalized(
+        block_height: u64,
+        block_hash: impl AsRef<str>,
+        client: &RuskHttpClient,
+    ) -> Result<bool, Error> {
+        let query = format!(
+            "query {{ checkBlock(height: {}, hash: \"{}\", onlyFinalized: true) }}",
+            block_height,
+            block_hash.as_ref()
+        );
+        let response = Self::gql_query(client, query).await?;
+        let result = serde_json::from_slice::<CheckBlockResponse>(&response)?;
+        Ok(result.check_block)
+    }
+
     pub async fn block_height(client: &RuskHttpClient) -> Result<u64, Error> {
         let query = "query { block(height: -1) {header { height}} }";
         let response = Self::gql_query(client, query).await?;
@@ -61,6 +61,16 @@ impl RuskHttpClient {
         I: rkyv::Serialize<rkyv::ser::serializers::AllocSerializer<N>>,
     {
         let data = rkyv::to_bytes(value).map_err(|_| Error::Rkyv)?.to_vec();
+        self.contract_query_raw(contract, method, data).await
+    }
+
+    /// Utility for querying the rusk VM with already serialized arguments
+    pub async fn contract_query_raw(
+        &self,
+        contract: &str,
+        method: &str,
+        data: Vec<u8>,
+    ) -> Result<Vec<u8>, Error> {
         let request = RuskRequest::new(method, data);
 
         let response = self.call_raw(1, contract, &request, false).await?;
@@ -16,6 +16,9 @@ use rkyv::{check_archived_root, Archive, Deserialize, Infallible};
 
 pub type ContractId = [u8; 32];
 
+/// Method name no contract is expected to export
+const PROBE_METHOD: &str = "__contract_exists_probe";
+
 pub struct ContractInquirer {}
 
 impl ContractInquirer {
@@ -45,6 +48,35 @@ impl ContractInquirer {
         Ok(r)
     }
 
+    /// Calls a given query method of a given contract,
+    /// passing arguments as rkyv serialized bytes.
+    /// Returns raw response bytes, without deserializing them.
+    pub async fn query_contract_raw(
+        client: &RuskHttpClient,
+        args: Vec<u8>,
+        contract_id: ContractId,
+        method: impl AsRef<str>,
+    ) -> Result<Vec<u8>, Error> {
+        let contract_id = hex::encode(contract_id.as_slice());
+        client
+            .contract_query_raw(contract_id.as_ref(), method.as_ref(), args)
+            .await
+    }
+
+    /// Checks whether a contract is deployed.
+    ///
+    /// The node exposes no endpoint reporting the existence of a contract,
+    /// so a method no contract exports is queried instead. A contract
+    /// does not exist if the node reports that it does not exist, and exists
+    /// if the node fails on the missing method, naming it. Any other
+    /// response is an error.
+    pub async fn contract_exists(
+        client: &RuskHttpClient,
+        contract_id: ContractId,
+    ) -> Result<bool, Error> {
+        probe_result(Self::query_contract_raw(client, vec![], contract_id, PROBE_METHOD).await)
+    }
+
     /// Calls a given query method of a given contract.
     /// Returns response as a stream to be processed by the caller.
     pub async fn query_contract_with_feeder<A>(
@@ -73,3 +105,46 @@ impl ContractInquirer {
         Ok(stream)
     }
 }
+
+/// Interprets the response to the existence probe of a contract
+fn probe_result(response: Result<Vec<u8>, Error>) -> Result<bool, Error> {
+    match response {
+        Ok(_) => Ok(true),
+        Err(Error::Rusk(msg)) if msg.to_lowercase().contains("does not exist") => Ok(false),
+        Err(Error::Rusk(msg)) if msg.contains(PROBE_METHOD) => Ok(true),
+        Err(Error::Rusk(msg)) => Err(InvalidQueryResponse(
+            format!("unrecognised response to contract existence probe: {}", msg).into(),
+        )),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::borrow::Cow;
+
+    fn rusk(msg: &str) -> Result<Vec<u8>, Error> {
+        Err(Error::Rusk(msg.to_string()))
+    }
+
+    #[test]
+    fn probe_responses() {
+        // a contract exporting the method
+        assert!(probe_result(Ok(vec![])).unwrap());
+        assert!(!probe_result(rusk("Contract does not exist: [0, 1, 2]")).unwrap());
+        assert!(!probe_result(rusk("contract DOES NOT EXIST")).unwrap());
+        assert!(
+            probe_result(rusk(&format!("Panic: export `{}` not found", PROBE_METHOD))).unwrap()
+        );
+
+        match probe_result(rusk("Out of gas")) {
+            Err(InvalidQueryResponse(msg)) => assert!(msg.contains("Out of gas")),
+            r => panic!("{:?}", r),
+        }
+        match probe_result(Err(Error::NotFound(Cow::from("contract")))) {
+            Err(Error::NotFound(_)) => {}
+            r => panic!("{:?}", r),
+        }
+    }
+}
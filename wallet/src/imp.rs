@@ -12,7 +12,7 @@ use alloc::string::FromUtf8Error;
 use alloc::vec::Vec;
 use std::mem;
 
-use dusk_bytes::{Error as BytesError, Serializable};
+use dusk_bytes::Error as BytesError;
 use execution_core::transfer::phoenix::{NoteOpening, Prove, TxCircuitVec};
 use execution_core::{
     signatures::bls::{PublicKey as BlsPublicKey, SecretKey as BlsSecretKey},
@@ -207,7 +207,17 @@ where
 
         let notes = self.state.fetch_notes(&vk).map_err(Error::from_state_err)?;
 
-        // this part is a performance bottleneck and needs caching
+        // notes known to be spent are not checked again
+        let mut spent_positions = self
+            .state
+            .fetch_spent_positions(&vk)
+            .map_err(Error::from_state_err)?;
+        spent_positions.sort_unstable();
+        let notes: Vec<_> = notes
+            .into_iter()
+            .filter(|(n, _)| spent_positions.binary_search(n.pos()).is_err())
+            .collect();
+
         let nullifiers: Vec<_> = notes.iter().map(|(n, _)| n.gen_nullifier(sk)).collect();
 
         let mut existing_nullifiers: Vec<BlsScalar> = vec![];
@@ -219,10 +229,20 @@ where
             );
         }
 
-        let unspent_notes = notes
+        let (spent_notes, unspent_notes): (Vec<_>, Vec<_>) = notes
             .into_iter()
             .zip(nullifiers)
-            .filter(|(_, nullifier)| !existing_nullifiers.contains(nullifier))
+            .partition(|(_, nullifier)| existing_nullifiers.contains(nullifier));
+
+        if !spent_notes.is_empty() {
+            let positions: Vec<u64> = spent_notes.iter().map(|((n, _), _)| *n.pos()).collect();
+            self.state
+                .store_spent_positions(&vk, &positions)
+                .map_err(Error::from_state_err)?;
+        }
+
+        let unspent_notes = unspent_notes
+            .into_iter()
             .map(|((note, _), _)| note)
             .collect();
 
@@ -464,13 +484,7 @@ where
             .map_err(Error::from_state_err)?;
         let chain_id = self.state.fetch_chain_id().map_err(Error::from_state_err)?;
 
-        println!(
-            "account {} fetched: {:?}",
-            bs58::encode(moonlight_pk.to_bytes()).into_string(),
-            acc_data
-        );
-
-        let result = self.moonlight_transaction(
+        self.moonlight_transaction(
             &moonlight_sk,
             None,
             0,
@@ -480,20 +494,7 @@ where
             acc_data.nonce + 1,
             chain_id,
             Some(exec.into()),
-        );
-
-        let acc_data = self
-            .state
-            .fetch_account(&moonlight_pk)
-            .map_err(Error::from_state_err)?;
-
-        println!(
-            "account {} fetched: {:?}",
-            bs58::encode(moonlight_pk.to_bytes()).into_string(),
-            acc_data
-        );
-
-        result
+        )
     }
 
     /// Transfer Dusk in the form of Phoenix notes from one key to another.
@@ -524,6 +525,39 @@ where
         )
     }
 
+    /// Transfer Dusk from one Moonlight account to another.
+    pub fn moonlight_transfer(
+        &self,
+        sender_index: u64,
+        receiver_pk: &BlsPublicKey,
+        value: u64,
+        gas_limit: u64,
+        gas_price: u64,
+    ) -> Result<Transaction, Error<S, SC, PC>> {
+        let moonlight_sk = self
+            .store
+            .fetch_account_secret_key(sender_index)
+            .map_err(Error::from_store_err)?;
+        let moonlight_pk = BlsPublicKey::from(&moonlight_sk);
+        let acc_data = self
+            .state
+            .fetch_account(&moonlight_pk)
+            .map_err(Error::from_state_err)?;
+        let chain_id = self.state.fetch_chain_id().map_err(Error::from_state_err)?;
+
+        self.moonlight_transaction(
+            &moonlight_sk,
+            Some(*receiver_pk),
+            value,
+            0,
+            gas_limit,
+            gas_price,
+            acc_data.nonce + 1,
+            chain_id,
+            None::<TransactionData>,
+        )
+    }
+
     /// Gets the balance of a key.
     pub fn get_balance(&self, sk_index: u64) -> Result<BalanceInfo, Error<S, SC, PC>> {
         let sender_sk = self
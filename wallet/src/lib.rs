@@ -106,6 +106,32 @@ pub fn derive_stake_sk(seed: &[u8; 64], index: u64) -> BlsSecretKey {
     BlsSecretKey::random(&mut rng)
 }
 
+/// Generates a Phoenix secret key from its seed and index, as the rusk
+/// wallet does.
+///
+/// Same as [`derive_sk`], except that the index is a single byte.
+pub fn derive_phoenix_sk(seed: &[u8; 64], index: u8) -> SecretKey {
+    SecretKey::random(&mut rng_with_index(seed, index, b"SSK"))
+}
+
+/// Generates a BLS secret key, e.g. of a Moonlight account, from its seed
+/// and index, as the rusk wallet does.
+///
+/// Same as [`derive_stake_sk`], except that the index is a single byte.
+pub fn derive_bls_sk(seed: &[u8; 64], index: u8) -> BlsSecretKey {
+    BlsSecretKey::random(&mut rng_with_index(seed, index, b"SK"))
+}
+
+fn rng_with_index(seed: &[u8; 64], index: u8, termination: &[u8]) -> ChaCha12Rng {
+    let mut hash = Sha256::new();
+
+    hash.update(seed);
+    hash.update(index.to_le_bytes());
+    hash.update(termination);
+
+    ChaCha12Rng::from_seed(hash.finalize().into())
+}
+
 /// Types that are client of the prover.
 // todo: naming - this client is not only prover client but prover and/or propagation client
 pub trait ProverClient {
@@ -149,6 +175,23 @@ pub trait StateClient {
         nullifiers: &[BlsScalar],
     ) -> Result<Vec<BlsScalar>, Self::Error>;
 
+    /// Returns the positions of the notes of a view key known to be spent,
+    /// in any order, which are then not checked again.
+    ///
+    /// The provided implementation knows of no spent notes. It may be
+    /// reimplemented to remember notes found spent by
+    /// [`store_spent_positions`](StateClient::store_spent_positions).
+    fn fetch_spent_positions(&self, _vk: &ViewKey) -> Result<Vec<u64>, Self::Error> {
+        Ok(Vec::new())
+    }
+
+    /// Records the positions of notes of a view key found to be spent.
+    ///
+    /// The provided implementation does nothing.
+    fn store_spent_positions(&self, _vk: &ViewKey, _positions: &[u64]) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
     /// Queries the node to find the opening for a specific note.
     fn fetch_opening(&self, note: &Note) -> Result<NoteOpening, Self::Error>;
 